@@ -1,17 +1,18 @@
-use std::time::Instant;
+use std::{path::PathBuf, time::Instant};
 
 use egui::{Color32, Separator};
 
 use ray::oidn::OIND;
-use ray::render::hittable::HittableVec;
-use ray::render::material::Material;
+use ray::render::camera::CameraSettings;
+use ray::render::environment::Environment;
 use ray::render::render;
-use ray::render::scene::{Scene, SceneRef};
+use ray::render::scene::Scene;
 use ray::render::texture::Texture;
-use ray::vec_repo::{Id, VecRepo};
+use ray::vec_repo::Id;
 
 use super::logger::LOGGER;
 use super::views;
+use crate::io;
 
 pub trait GuiElement {
     fn show(&mut self, ctx: &egui::Context);
@@ -45,12 +46,16 @@ fn show_view_as_side_panel(
 
 #[derive(Default)]
 pub struct ProjectEditor {
+    /// Where the scene was last loaded from or saved to.
+    path: Option<PathBuf>,
     //temporary. will be replaced with editors later.
-    hittable: HittableVec,
     background: Id<Texture>,
-    materials: VecRepo<Material>,
+    environment: Environment,
     //===========================
     cameras_editor: (views::CamerasEditor, bool),
+    object_editor: (views::ObjectEditor, bool),
+    lights_editor: (views::LightsEditor, bool),
+    materials_editor: (views::MaterialsEditor, bool),
     texture_editor: (views::TextureEditor, bool),
     previews: Vec<(views::RenderedImageView, bool)>,
 }
@@ -58,53 +63,104 @@ pub struct ProjectEditor {
 impl ProjectEditor {
     pub fn from_scene(scene: Scene) -> Self {
         let cameras_editor = (views::CamerasEditor::with_default(scene.camera), false);
+        let object_editor = (
+            views::ObjectEditor::new(scene.hittable, scene.object_names),
+            false,
+        );
+        let lights_editor = (views::LightsEditor::from(scene.lights), false);
+        let materials_editor = (views::MaterialsEditor::from(scene.materials), false);
         let texture_editor = (views::TextureEditor::from(scene.textures), false);
 
         Self {
-            hittable: scene.hittable,
+            path: None,
             background: scene.background,
-            materials: scene.materials,
+            environment: scene.environment,
             cameras_editor,
+            object_editor,
+            lights_editor,
+            materials_editor,
             texture_editor,
             previews: Vec::new(),
         }
     }
 
+    /// The scene as edited, seen through `camera`.
+    fn scene(&self, camera: CameraSettings) -> Scene {
+        Scene {
+            hittable: self.object_editor.0.get_objects().clone(),
+            lights: self.lights_editor.0.get_lights().clone(),
+            environment: self.environment.clone(),
+            camera,
+            background: self.background,
+            materials: self.materials_editor.0.get_repo().clone(),
+            textures: self.texture_editor.0.get_repo().clone(),
+            object_names: self.object_editor.0.get_names().clone(),
+        }
+    }
+
+    /// Saves to the file last used, asking for one if there is none yet.
     pub fn save_scene(&mut self) {
-        rfd::FileDialog::new().save_file().and_then(|path| {
-            let scene = SceneRef {
-                hittable: &self.hittable,
-                camera: self.cameras_editor.0.last_chosen_camera(),
-                background: &self.background,
-                materials: &self.materials,
-                textures: self.texture_editor.0.get_repo(),
-            };
-            Some(
-                rmp_serde::to_vec(&scene)
-                    .map(|data| miniz_oxide::deflate::compress_to_vec(&data, 10))
-                    .map(|contents| std::fs::write(path, contents))
-                    .unwrap(),
-            )
-        });
+        match self.path.clone() {
+            Some(path) => self.write_scene(path),
+            None => self.save_scene_as(),
+        }
+    }
+
+    pub fn save_scene_as(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().save_file() {
+            self.write_scene(path);
+        }
+    }
+
+    fn write_scene(&mut self, path: PathBuf) {
+        let scene = self.scene(self.cameras_editor.0.last_chosen_camera().clone());
+        match io::write_scene(&path, &scene) {
+            Ok(()) => LOGGER.log(format!("Saved {}", path.display())),
+            Err(err) => {
+                LOGGER.log(format!("Could not save {}: {}", path.display(), err));
+                return;
+            }
+        }
+        self.path = Some(path);
     }
 
+    /// Removes the materials and textures nothing uses, renumbering the rest.
+    fn compact_scene(&mut self) {
+        let mut scene = self.scene(self.cameras_editor.0.last_chosen_camera().clone());
+        let removed = scene.compact();
+        self.background = scene.background;
+        self.object_editor.0 = views::ObjectEditor::new(scene.hittable, scene.object_names);
+        self.materials_editor.0 = views::MaterialsEditor::from(scene.materials);
+        self.texture_editor.0 = views::TextureEditor::from(scene.textures);
+        LOGGER.log(format!(
+            "Removed {} unused materials and {} unused textures",
+            removed.materials.len(),
+            removed.textures.len()
+        ));
+    }
+
+    /// Replaces everything being edited with the picked scene. Open panels and renders stay.
     pub fn load_scene(&mut self) {
-        rfd::FileDialog::new()
-            .pick_file()
-            .and_then(|path| std::fs::read(path).ok())
-            .and_then(|vec| miniz_oxide::inflate::decompress_to_vec(&vec).ok())
-            .and_then(|vec| rmp_serde::from_slice(&vec).ok())
-            .and_then(|scene: Scene| {
-                self.cameras_editor = (
-                    views::CamerasEditor::with_default(scene.camera),
-                    self.cameras_editor.1,
-                );
-                self.texture_editor = (
-                    views::TextureEditor::from(scene.textures),
-                    self.texture_editor.1,
-                );
-                Some(42)
-            });
+        let Some(path) = rfd::FileDialog::new().pick_file() else {
+            return;
+        };
+        let scene = match io::read_scene(&path) {
+            Ok(scene) => scene,
+            Err(err) => {
+                LOGGER.log(format!("Could not load {}: {}", path.display(), err));
+                return;
+            }
+        };
+        let mut loaded = Self::from_scene(scene);
+        loaded.cameras_editor.1 = self.cameras_editor.1;
+        loaded.object_editor.1 = self.object_editor.1;
+        loaded.lights_editor.1 = self.lights_editor.1;
+        loaded.materials_editor.1 = self.materials_editor.1;
+        loaded.texture_editor.1 = self.texture_editor.1;
+        loaded.previews = std::mem::take(&mut self.previews);
+        LOGGER.log(format!("Loaded {}", path.display()));
+        loaded.path = Some(path);
+        *self = loaded;
     }
 }
 
@@ -120,8 +176,24 @@ impl GuiElement for ProjectEditor {
                     {
                         self.cameras_editor.1 = !self.cameras_editor.1;
                     }
-                    if ui.selectable_label(false, "Objects").clicked() {}
-                    if ui.selectable_label(false, "Materials").clicked() {}
+                    if ui
+                        .selectable_label(self.object_editor.1, "Objects")
+                        .clicked()
+                    {
+                        self.object_editor.1 = !self.object_editor.1;
+                    }
+                    if ui
+                        .selectable_label(self.lights_editor.1, "Lights")
+                        .clicked()
+                    {
+                        self.lights_editor.1 = !self.lights_editor.1;
+                    }
+                    if ui
+                        .selectable_label(self.materials_editor.1, "Materials")
+                        .clicked()
+                    {
+                        self.materials_editor.1 = !self.materials_editor.1;
+                    }
                     if ui
                         .selectable_label(self.texture_editor.1, "Textures")
                         .clicked()
@@ -134,11 +206,37 @@ impl GuiElement for ProjectEditor {
                     if ui.selectable_label(false, "Save").clicked() {
                         self.save_scene()
                     }
-                    ui.add_space(ui.available_width() - 240.0);
+                    if ui.selectable_label(false, "Save As").clicked() {
+                        self.save_scene_as()
+                    }
+                    if ui
+                        .selectable_label(false, "Compact")
+                        .on_hover_text("Remove the materials and textures nothing uses")
+                        .clicked()
+                    {
+                        self.compact_scene()
+                    }
+                    ui.add_space(ui.available_width() - 535.0);
                     ui.label("Background:");
                     self.texture_editor
                         .0
                         .texture_picker(ui, &mut self.background);
+                    ui.add(
+                        egui::DragValue::new(&mut self.environment.rotation)
+                            .speed(1.0)
+                            .clamp_range(-180.0..=180.0)
+                            .suffix("°"),
+                    )
+                    .on_hover_text("Rotation");
+                    ui.add(
+                        egui::DragValue::new(&mut self.environment.intensity)
+                            .speed(0.05)
+                            .clamp_range(0.0..=f32::INFINITY)
+                            .prefix("x"),
+                    )
+                    .on_hover_text("Intensity");
+                    ui.checkbox(&mut self.environment.transparent, "Transparent")
+                        .on_hover_text("Hide the background from the camera");
                 });
             });
 
@@ -170,6 +268,32 @@ impl GuiElement for ProjectEditor {
             egui::panel::Side::Left,
         );
 
+        show_view_as_side_panel(
+            ctx,
+            &mut self.object_editor.0,
+            self.object_editor.1,
+            egui::panel::Side::Left,
+        );
+
+        show_view_as_side_panel(
+            ctx,
+            &mut self.lights_editor.0,
+            self.lights_editor.1,
+            egui::panel::Side::Left,
+        );
+
+        let (materials_editor, open) = &mut self.materials_editor;
+        let textures = &self.texture_editor.0;
+        egui::SidePanel::right(egui::Id::new(materials_editor.title())).show_animated(
+            ctx,
+            *open,
+            |ui| {
+                ui.heading(materials_editor.title());
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| materials_editor.ui(ui, textures))
+            },
+        );
+
         show_view_as_side_panel(
             ctx,
             &mut self.texture_editor.0,
@@ -183,13 +307,7 @@ impl GuiElement for ProjectEditor {
 
         if let Some(camera) = self.cameras_editor.0.chosen_camera() {
             let title = format!("Render {}", self.previews.len());
-            let scene = Scene {
-                hittable: self.hittable.clone(),
-                camera,
-                background: self.background,
-                materials: self.materials.clone(),
-                textures: self.texture_editor.0.get_repo().clone(),
-            };
+            let scene = self.scene(camera);
             let (tx, rx) = std::sync::mpsc::channel();
             std::thread::spawn(move || {
                 let now = Instant::now();
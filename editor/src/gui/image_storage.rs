@@ -1,10 +1,11 @@
-use std::{collections::HashMap, sync::Mutex};
+use std::{collections::HashMap, hash::Hash, sync::Mutex};
 
 use egui::ColorImage;
 use egui_extras::RetainedImage;
+use image::DynamicImage;
 use lazy_static::lazy_static;
 
-use ray::render::texture::Image;
+use ray::render::texture::{ColourSpace, HdrImage, Image};
 
 lazy_static! {
     pub static ref IMAGE_STORAGE: ImageStorage = ImageStorage::default();
@@ -13,6 +14,8 @@ lazy_static! {
 #[derive(Default)]
 pub struct ImageStorage {
     data: Mutex<HashMap<Image, RetainedImage>>,
+    /// The preview depends on the colour space, which images don't compare by.
+    hdr_data: Mutex<HashMap<(HdrImage, ColourSpace), RetainedImage>>,
 }
 
 impl ImageStorage {
@@ -21,14 +24,42 @@ impl ImageStorage {
     }
 
     pub fn with_retained(&self, img: &Image, f: impl FnOnce(&RetainedImage)) {
-        self.data
-            .lock()
-            .and_then(|mut data| {
-                if !data.contains_key(img) {
-                    data.insert(img.clone(), self.image_to_retained(img));
+        Self::retained(&self.data, img, || self.image_to_retained(img), f);
+    }
+
+    pub fn add_retained_hdr(&self, img: &HdrImage) {
+        self.with_retained_hdr(img, |_x| {});
+    }
+
+    /// Shows `img` gamma corrected unless already sRGB, with everything brighter than white clipped.
+    pub fn with_retained_hdr(&self, img: &HdrImage, f: impl FnOnce(&RetainedImage)) {
+        Self::retained(
+            &self.hdr_data,
+            &(img.clone(), img.colour_space()),
+            || {
+                let mut preview = (**img).clone();
+                if img.colour_space() != ColourSpace::Srgb {
+                    preview.iter_mut().for_each(|c| *c = c.max(0.0).sqrt());
+                }
+                self.image_to_retained(&DynamicImage::ImageRgb32F(preview).into_rgb8())
+            },
+            f,
+        );
+    }
+
+    fn retained<K: Hash + Eq + Clone>(
+        data: &Mutex<HashMap<K, RetainedImage>>,
+        key: &K,
+        to_retained: impl FnOnce() -> RetainedImage,
+        f: impl FnOnce(&RetainedImage),
+    ) {
+        data.lock()
+            .map(|mut data| {
+                if !data.contains_key(key) {
+                    data.insert(key.clone(), to_retained());
                 }
-                let rtimg = data.get(img).expect("RetainedImage MUST be there");
-                Ok(f(rtimg))
+                let rtimg = data.get(key).expect("RetainedImage MUST be there");
+                f(rtimg)
             })
             .expect("ImageStorage MUST never fail");
     }
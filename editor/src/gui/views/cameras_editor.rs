@@ -76,6 +76,18 @@ impl View for CamerasEditor {
             ui.add(egui::DragValue::new(&mut self.default.aperture).speed(0.05));
             ui.end_row();
 
+            ui.label("Shutter:");
+            shutter_editor(
+                ui,
+                &mut self.default.shutter_open,
+                &mut self.default.shutter_close,
+            );
+            ui.end_row();
+
+            ui.label("Spectral:");
+            ui.checkbox(&mut self.default.spectral, "");
+            ui.end_row();
+
             ui.label("Look At:");
             point3_editor(ui, &mut self.default.look_at);
             ui.end_row();
@@ -112,6 +124,14 @@ impl View for CamerasEditor {
                 ui.add(egui::DragValue::new(&mut c.aperture).speed(0.05));
                 ui.end_row();
 
+                ui.label("Shutter:");
+                shutter_editor(ui, &mut c.shutter_open, &mut c.shutter_close);
+                ui.end_row();
+
+                ui.label("Spectral:");
+                ui.checkbox(&mut c.spectral, "");
+                ui.end_row();
+
                 ui.label("Look At:");
                 point3_editor(ui, &mut c.look_at);
                 ui.end_row();
@@ -123,3 +143,15 @@ impl View for CamerasEditor {
         }
     }
 }
+
+fn shutter_editor(ui: &mut egui::Ui, open: &mut f64, close: &mut f64) {
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(open).speed(0.01).prefix("Open: "));
+        ui.add(
+            egui::DragValue::new(close)
+                .speed(0.01)
+                .clamp_range(*open..=f64::INFINITY)
+                .prefix("Close: "),
+        );
+    });
+}
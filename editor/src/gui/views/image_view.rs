@@ -1,6 +1,7 @@
 use std::{mem::take, sync::mpsc::Receiver};
 
-use crate::gui::image_storage::IMAGE_STORAGE;
+use crate::gui::{image_storage::IMAGE_STORAGE, logger::LOGGER};
+use crate::io;
 use ray::render::{texture::Image, RenderedImage};
 
 use super::View;
@@ -19,6 +20,8 @@ pub enum RenderedImageView {
         albedo: Image,
         normal: Image,
         denoised: Image,
+        /// Full precision passes, for saving.
+        rendered: Box<RenderedImage>,
         viewed_option: u8,
         size: (u32, u32),
     },
@@ -40,6 +43,7 @@ impl View for RenderedImageView {
                 albedo: _,
                 normal: _,
                 denoised: _,
+                rendered: _,
                 viewed_option: _,
                 size: _,
             } => &title,
@@ -50,25 +54,26 @@ impl View for RenderedImageView {
         match self {
             Self::Waiting { title, rx } => {
                 if let Ok(mut img) = rx.try_recv() {
-                    fn imgf32_to_imgu8(img: Rgb32FImage) -> RgbImage {
-                        DynamicImage::ImageRgb32F(img).into_rgb8()
+                    fn imgf32_to_imgu8(img: &Rgb32FImage) -> RgbImage {
+                        DynamicImage::ImageRgb32F(img.clone()).into_rgb8()
                     }
 
                     let size = img.colour.dimensions();
 
-                    let colour = Image::new(imgf32_to_imgu8(img.colour));
-                    let albedo = Image::new(imgf32_to_imgu8(img.albedo));
+                    let colour = Image::new(imgf32_to_imgu8(&img.colour));
+                    let albedo = Image::new(imgf32_to_imgu8(&img.albedo));
                     for n in img.normal.as_mut().iter_mut() {
                         *n = (*n + 1.0) / 2.0;
                     }
-                    let normal = Image::new(imgf32_to_imgu8(img.normal));
-                    let denoised = Image::new(imgf32_to_imgu8(img.denoised));
+                    let normal = Image::new(imgf32_to_imgu8(&img.normal));
+                    let denoised = Image::new(imgf32_to_imgu8(&img.denoised));
                     *self = RenderedImageView::Ready {
                         title: take(title),
                         colour,
                         albedo,
                         normal,
                         denoised,
+                        rendered: Box::new(img),
                         viewed_option: 0u8,
                         size,
                     }
@@ -81,6 +86,7 @@ impl View for RenderedImageView {
                 albedo,
                 normal,
                 denoised,
+                rendered,
                 viewed_option,
                 size,
             } => {
@@ -122,10 +128,32 @@ impl View for RenderedImageView {
                 ui.horizontal_wrapped(|ui| {
                     ui.label(format!("Size: {}x{}", size.0, size.1));
                     if ui.button("Save").clicked() {
-                        eprintln!("TODO: Save image");
+                        save_pass(rendered, *viewed_option);
                     }
                 });
             }
         }
     }
 }
+
+/// Asks where to save the pass shown as `viewed_option`, with the alpha channel if any.
+fn save_pass(rendered: &RenderedImage, viewed_option: u8) {
+    let (image, srgb) = match viewed_option {
+        0 => (&rendered.colour, true),
+        1 => (&rendered.albedo, false),
+        2 => (&rendered.normal, false),
+        3 => (&rendered.denoised, true),
+        _ => unreachable!(),
+    };
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("PNG", &["png"])
+        .add_filter("OpenEXR", &["exr"])
+        .save_file()
+    else {
+        return;
+    };
+    match io::save_render(&path, image, rendered.alpha.as_ref(), srgb) {
+        Ok(()) => LOGGER.log(format!("Saved {}", path.display())),
+        Err(err) => LOGGER.log(format!("Could not save {}: {}", path.display(), err)),
+    }
+}
@@ -0,0 +1,167 @@
+use egui::Ui;
+
+use ray::render::{
+    light::{Light, LightTrait, PointLight, RectLight, SphereLight, SpotLight, SunLight},
+    Colour, Point3, Vector3,
+};
+
+use super::{direction_editor, grid, length_editor, point3_editor, vector_editor, View};
+
+#[derive(Default)]
+pub struct LightsEditor {
+    lights: Vec<Light>,
+}
+
+impl From<Vec<Light>> for LightsEditor {
+    fn from(value: Vec<Light>) -> Self {
+        Self { lights: value }
+    }
+}
+
+impl LightsEditor {
+    pub fn get_lights(&self) -> &Vec<Light> {
+        &self.lights
+    }
+}
+
+impl View for LightsEditor {
+    fn title(&self) -> &str {
+        "Lights"
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.menu_button("Add Light", |ui| {
+            for light in default_lights() {
+                if ui.button(light.name()).clicked() {
+                    self.lights.push(light);
+                    ui.close_menu();
+                }
+            }
+        });
+        ui.separator();
+
+        let mut removed = None;
+        for (idx, light) in self.lights.iter_mut().enumerate() {
+            egui::CollapsingHeader::new(format!("{} {}", light.name(), idx))
+                .id_source(ui.auto_id_with(idx))
+                .show(ui, |ui| {
+                    grid(ui, "light", 2, false).show(ui, |ui| light_editor(ui, light));
+                    if ui.button("Remove").clicked() {
+                        removed = Some(idx);
+                    }
+                });
+        }
+        if let Some(idx) = removed {
+            self.lights.remove(idx);
+        }
+    }
+}
+
+fn default_lights() -> [Light; 5] {
+    let white = Colour::new(1.0, 1.0, 1.0);
+    [
+        PointLight {
+            position: Point3::new(0.0, 5.0, 0.0),
+            colour: white,
+            intensity: 100.0,
+        }
+        .into(),
+        SpotLight {
+            position: Point3::new(0.0, 5.0, 0.0),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+            colour: white,
+            intensity: 100.0,
+            angle: 30.0,
+            falloff: 0.2,
+        }
+        .into(),
+        SunLight {
+            direction: Vector3::new(0.3, 1.0, 0.2),
+            colour: white,
+            strength: 3.0,
+            angular_diameter: 0.5,
+        }
+        .into(),
+        SphereLight {
+            center: Point3::new(0.0, 5.0, 0.0),
+            radius: 0.5,
+            colour: white,
+            strength: 10.0,
+        }
+        .into(),
+        RectLight {
+            corner: Point3::new(-0.5, 5.0, -0.5),
+            u: Vector3::new(1.0, 0.0, 0.0),
+            v: Vector3::new(0.0, 0.0, 1.0),
+            colour: white,
+            strength: 10.0,
+            two_sided: false,
+        }
+        .into(),
+    ]
+}
+
+fn light_editor(ui: &mut Ui, light: &mut Light) {
+    match light {
+        Light::PointLight(light) => {
+            position_editor(ui, &mut light.position);
+            colour_editor(ui, &mut light.colour, "Intensity: ", &mut light.intensity);
+        }
+        Light::SpotLight(light) => {
+            position_editor(ui, &mut light.position);
+            direction_editor(ui, "Direction: ", &mut light.direction);
+            colour_editor(ui, &mut light.colour, "Intensity: ", &mut light.intensity);
+            angle_editor(ui, "Angle: ", &mut light.angle, 90.0);
+            ui.label("Falloff: ");
+            ui.add(egui::Slider::new(&mut light.falloff, 0.0..=1.0));
+            ui.end_row();
+        }
+        Light::SunLight(light) => {
+            direction_editor(ui, "Direction: ", &mut light.direction);
+            colour_editor(ui, &mut light.colour, "Strength: ", &mut light.strength);
+            angle_editor(ui, "Angular Diameter: ", &mut light.angular_diameter, 45.0);
+        }
+        Light::SphereLight(light) => {
+            position_editor(ui, &mut light.center);
+            length_editor(ui, "Radius: ", &mut light.radius);
+            colour_editor(ui, &mut light.colour, "Strength: ", &mut light.strength);
+        }
+        Light::RectLight(light) => {
+            ui.label("Corner: ");
+            point3_editor(ui, &mut light.corner);
+            ui.end_row();
+            vector_editor(ui, "Edge U: ", &mut light.u, 0.5);
+            vector_editor(ui, "Edge V: ", &mut light.v, 0.5);
+            colour_editor(ui, &mut light.colour, "Strength: ", &mut light.strength);
+            ui.label("Two Sided: ");
+            ui.checkbox(&mut light.two_sided, "");
+            ui.end_row();
+        }
+    }
+}
+
+fn position_editor(ui: &mut Ui, position: &mut Point3) {
+    ui.label("Position: ");
+    point3_editor(ui, position);
+    ui.end_row();
+}
+
+fn colour_editor(ui: &mut Ui, colour: &mut Colour, label: &str, strength: &mut f32) {
+    ui.label("Colour: ");
+    ui.color_edit_button_rgb(colour.as_mut());
+    ui.end_row();
+
+    ui.label(label);
+    ui.add(
+        egui::DragValue::new(strength)
+            .speed(0.1)
+            .clamp_range(0.0..=f32::INFINITY),
+    );
+    ui.end_row();
+}
+
+fn angle_editor(ui: &mut Ui, label: &str, degrees: &mut f64, max: f64) {
+    ui.label(label);
+    ui.add(egui::Slider::new(degrees, 0.0..=max).suffix("°"));
+    ui.end_row();
+}
@@ -0,0 +1,228 @@
+use egui::Ui;
+
+use ray::{
+    render::material::{Cutout, MaskChannel, Material, NormalMap, NormalMapKind},
+    vec_repo::{Id, VecRepo},
+};
+
+use crate::gui::logger::LOGGER;
+
+use super::{asset_label, grid, name_editor, TextureEditor};
+
+/// How far `reaches` follows bases before assuming the worst.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Default)]
+pub struct MaterialsEditor {
+    materials: VecRepo<Material>,
+}
+
+impl From<VecRepo<Material>> for MaterialsEditor {
+    fn from(value: VecRepo<Material>) -> Self {
+        Self { materials: value }
+    }
+}
+
+impl MaterialsEditor {
+    pub fn get_repo(&self) -> &VecRepo<Material> {
+        &self.materials
+    }
+
+    pub fn title(&self) -> &str {
+        "Materials"
+    }
+
+    /// Needs the textures to pick normal, bump and cutout maps from.
+    pub fn ui(&mut self, ui: &mut Ui, textures: &TextureEditor) {
+        let labels: Vec<_> = self
+            .materials
+            .ids()
+            .map(|id| (id, asset_label(&self.materials, "Material", id)))
+            .collect();
+        // Bases each material may pick, leaving out those built on it
+        let bases: Vec<Vec<_>> = labels
+            .iter()
+            .map(|(id, _)| {
+                labels
+                    .iter()
+                    .filter(|(base, _)| !reaches(&self.materials, *base, *id, 0))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        let names = self.materials.names().clone();
+        let mut wrapped = None;
+        let mut renamed = None;
+        for (((id, material), (_, label)), bases) in
+            self.materials.iter_with_ids_mut().zip(&labels).zip(&bases)
+        {
+            egui::CollapsingHeader::new(format!("{} ({})", label, material_name(material)))
+                .id_source(ui.auto_id_with(id))
+                .show(ui, |ui| {
+                    grid(ui, "name", 2, false).show(ui, |ui| {
+                        if let Some(name) = name_editor(ui, ("material", id), names.name(id)) {
+                            renamed = Some((id, name));
+                        }
+                    });
+                    match material {
+                        Material::NormalMap(normal_map) => {
+                            grid(ui, "normal_map", 2, false)
+                                .show(ui, |ui| normal_map_editor(ui, normal_map, bases, textures));
+                        }
+                        Material::Cutout(cutout) => {
+                            grid(ui, "cutout", 2, false)
+                                .show(ui, |ui| cutout_editor(ui, cutout, bases, textures));
+                        }
+                        _ => {
+                            ui.horizontal(|ui| {
+                                if ui.button("Add Normal Map").clicked() {
+                                    wrapped = Some((id, Wrapper::NormalMap));
+                                }
+                                if ui.button("Add Cutout").clicked() {
+                                    wrapped = Some((id, Wrapper::Cutout));
+                                }
+                            });
+                        }
+                    }
+                });
+        }
+        if let Some((id, name)) = renamed {
+            if !self.materials.set_name(id, &name) {
+                LOGGER.log(format!("Another material is already named {}", name));
+            }
+        }
+        if let Some((id, wrapper)) = wrapped {
+            self.wrap(id, wrapper);
+        }
+    }
+
+    /// Puts `wrapper` in place of `id`, so everything using it gets the wrapper.
+    fn wrap(&mut self, id: Id<Material>, wrapper: Wrapper) {
+        let base = self.materials.get(id).clone();
+        if base
+            .references()
+            .0
+            .into_iter()
+            .any(|used| reaches(&self.materials, used, id, 0))
+        {
+            LOGGER.log(format!(
+                "{} is built on itself, so it can't be wrapped",
+                asset_label(&self.materials, "Material", id)
+            ));
+            return;
+        }
+        let base = self.materials.insert(base);
+        if let Some(material) = self.materials.get_mut(id) {
+            *material = match wrapper {
+                Wrapper::NormalMap => {
+                    NormalMap::new(base, Id::default(), NormalMapKind::Normal).into()
+                }
+                Wrapper::Cutout => Cutout::new(base, Id::default()).into(),
+            };
+        }
+    }
+}
+
+/// Whether `from` is `target` or is built on it. Too deep to tell counts as
+/// reaching it, so a picked base can never close a cycle.
+fn reaches(
+    materials: &VecRepo<Material>,
+    from: Id<Material>,
+    target: Id<Material>,
+    depth: usize,
+) -> bool {
+    from == target
+        || depth >= MAX_DEPTH
+        || materials
+            .get(from)
+            .references()
+            .0
+            .into_iter()
+            .any(|base| reaches(materials, base, target, depth + 1))
+}
+
+/// Materials that change another one, put in its place.
+enum Wrapper {
+    NormalMap,
+    Cutout,
+}
+
+fn base_picker(ui: &mut Ui, base: &mut Id<Material>, labels: &[(Id<Material>, String)]) {
+    ui.label("Base: ");
+    let selected = labels
+        .iter()
+        .find(|(id, _)| id == base)
+        .map_or_else(|| format!("Material {}", base), |(_, label)| label.clone());
+    egui::ComboBox::from_id_source(ui.auto_id_with("base"))
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (id, label) in labels {
+                ui.selectable_value(base, *id, label);
+            }
+        });
+    ui.end_row();
+}
+
+fn normal_map_editor(
+    ui: &mut Ui,
+    normal_map: &mut NormalMap,
+    labels: &[(Id<Material>, String)],
+    textures: &TextureEditor,
+) {
+    base_picker(ui, &mut normal_map.base, labels);
+
+    ui.label("Map: ");
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut normal_map.kind, NormalMapKind::Normal, "Normal");
+        ui.radio_value(&mut normal_map.kind, NormalMapKind::Bump, "Bump");
+    });
+    ui.end_row();
+
+    ui.label("Texture: ");
+    ui.horizontal(|ui| textures.texture_picker(ui, &mut normal_map.texture));
+    ui.end_row();
+
+    ui.label("Strength: ");
+    ui.add(
+        egui::DragValue::new(&mut normal_map.strength)
+            .speed(0.01)
+            .clamp_range(0.0..=f64::INFINITY),
+    );
+    ui.end_row();
+}
+
+fn cutout_editor(
+    ui: &mut Ui,
+    cutout: &mut Cutout,
+    labels: &[(Id<Material>, String)],
+    textures: &TextureEditor,
+) {
+    base_picker(ui, &mut cutout.base, labels);
+
+    ui.label("Mask: ");
+    ui.horizontal(|ui| textures.texture_picker(ui, &mut cutout.mask));
+    ui.end_row();
+
+    ui.label("Channel: ");
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut cutout.channel, MaskChannel::Alpha, "Alpha");
+        ui.radio_value(&mut cutout.channel, MaskChannel::Luminance, "Luminance");
+    });
+    ui.end_row();
+}
+
+fn material_name(material: &Material) -> &'static str {
+    match material {
+        Material::Lambertian(_) => "Lambertian",
+        Material::Metal(_) => "Metal",
+        Material::Dielectric(_) => "Dielectric",
+        Material::DiffuseLight(_) => "Diffuse Light",
+        Material::Isotropic(_) => "Isotropic",
+        Material::Principled(_) => "Principled",
+        Material::Mix(_) => "Mix",
+        Material::Coated(_) => "Coated",
+        Material::Emissive(_) => "Emissive",
+        Material::NormalMap(_) => "Normal Map",
+        Material::Cutout(_) => "Cutout",
+    }
+}
@@ -1,6 +1,7 @@
 use egui::{Response, Ui};
 
 mod texture_editor;
+mod texture_nodes;
 
 pub use texture_editor::TextureEditor;
 
@@ -10,9 +11,19 @@ pub use image_view::RenderedImageView;
 mod cameras_editor;
 pub use cameras_editor::CamerasEditor;
 
-use ray::render::Point3;
+use ray::{
+    render::{Point3, Vector3},
+    vec_repo::{Id, VecRepo},
+};
 
 mod object_editor;
+pub use object_editor::ObjectEditor;
+
+mod lights_editor;
+pub use lights_editor::LightsEditor;
+
+mod materials_editor;
+pub use materials_editor::MaterialsEditor;
 
 pub trait View {
     fn title(&self) -> &str;
@@ -29,8 +40,64 @@ fn point3_editor(ui: &mut Ui, p: &mut Point3) -> Response {
     .inner
 }
 
+/// The name of `id`, or `kind` and its number if it has none.
+fn asset_label<T>(repo: &VecRepo<T>, kind: &str, id: Id<T>) -> String {
+    match repo.name(id) {
+        Some(name) => name.to_owned(),
+        None => format!("{} {}", kind, id),
+    }
+}
+
+/// Text field for a name. The name is only given back once editing ends,
+/// so a half typed one doesn't clash with another.
+fn name_editor(ui: &mut Ui, id_source: impl std::hash::Hash, name: Option<&str>) -> Option<String> {
+    let id = ui.make_persistent_id(id_source);
+    let name = name.unwrap_or_default();
+    let mut text = ui
+        .data_mut(|d| d.get_temp::<String>(id))
+        .unwrap_or_else(|| name.to_owned());
+    ui.label("Name: ");
+    let response = ui.text_edit_singleline(&mut text);
+    ui.end_row();
+    if response.has_focus() {
+        ui.data_mut(|d| d.insert_temp(id, text));
+        None
+    } else {
+        ui.data_mut(|d| d.remove::<String>(id));
+        (response.lost_focus() && text != name).then_some(text)
+    }
+}
+
 fn grid(ui: &mut Ui, name: &'static str, num_columns: usize, stripped: bool) -> egui::Grid {
     egui::Grid::new(ui.auto_id_with(name))
         .num_columns(num_columns)
         .striped(stripped)
 }
+
+fn vector_editor(ui: &mut Ui, label: &str, vec: &mut Vector3, speed: f64) -> egui::Response {
+    ui.label(label);
+    let response = ui
+        .horizontal(|ui| {
+            let x_field = ui.add(egui::DragValue::new(&mut vec.x).speed(speed).prefix("X: "));
+            let y_field = ui.add(egui::DragValue::new(&mut vec.y).speed(speed).prefix("Y: "));
+            let z_field = ui.add(egui::DragValue::new(&mut vec.z).speed(speed).prefix("Z: "));
+            x_field.union(y_field).union(z_field)
+        })
+        .inner;
+    ui.end_row();
+    response
+}
+
+fn direction_editor(ui: &mut Ui, label: &str, dir: &mut Vector3) {
+    vector_editor(ui, label, dir, 0.01);
+}
+
+fn length_editor(ui: &mut Ui, label: &str, value: &mut f64) {
+    ui.label(label);
+    ui.add(
+        egui::DragValue::new(value)
+            .speed(0.5)
+            .clamp_range(0.0..=f64::INFINITY),
+    );
+    ui.end_row();
+}
@@ -1,13 +1,245 @@
 use egui::Ui;
 
-use ray::render::hittable::sphere::Sphere;
+use ray::{
+    render::hittable::{
+        cone::Cone,
+        cuboid::Cuboid,
+        cylinder::Cylinder,
+        disk::Disk,
+        heightfield::Heightfield,
+        modifiers::{Keyframe, Transform},
+        moving_sphere::MovingSphere,
+        plane::Plane,
+        quad::Quad,
+        sphere::Sphere,
+        torus::Torus,
+        Hittable, HittableTrait, HittableVec,
+    },
+    vec_repo::Names,
+};
 
-use super::point3_editor;
+use crate::gui::logger::LOGGER;
+
+use super::{
+    direction_editor, grid, length_editor, name_editor, point3_editor, vector_editor, View,
+};
+
+#[derive(Default)]
+pub struct ObjectEditor {
+    objects: HittableVec,
+    names: Names<usize>,
+}
+
+impl ObjectEditor {
+    pub fn new(objects: HittableVec, names: Names<usize>) -> Self {
+        Self { objects, names }
+    }
+
+    pub fn get_objects(&self) -> &HittableVec {
+        &self.objects
+    }
+
+    pub fn get_names(&self) -> &Names<usize> {
+        &self.names
+    }
+}
+
+impl View for ObjectEditor {
+    fn title(&self) -> &str {
+        "Objects"
+    }
+
+    fn ui(&mut self, ui: &mut Ui) {
+        let names = &mut self.names;
+        for (idx, object) in self.objects.iter_mut().enumerate() {
+            let title = match names.name(idx) {
+                Some(name) => format!("{} ({})", name, object.name()),
+                None => format!("{} {}", object.name(), idx),
+            };
+            egui::CollapsingHeader::new(title)
+                .id_source(ui.auto_id_with(idx))
+                .show(ui, |ui| {
+                    grid(ui, "object", 2, false).show(ui, |ui| {
+                        if let Some(name) = name_editor(ui, ("object", idx), names.name(idx)) {
+                            if !names.set(idx, &name) {
+                                LOGGER.log(format!("Another object is already named {}", name));
+                            }
+                        }
+                        hittable_editor(ui, object)
+                    });
+                });
+        }
+    }
+}
+
+fn hittable_editor(ui: &mut Ui, hittable: &mut Hittable) {
+    match hittable {
+        Hittable::Sphere(sphere) => sphere_editor(ui, sphere),
+        Hittable::MovingSphere(sphere) => moving_sphere_editor(ui, sphere),
+        Hittable::Quad(quad) => quad_editor(ui, quad),
+        Hittable::Cuboid(cuboid) => cuboid_editor(ui, cuboid),
+        Hittable::Cylinder(cylinder) => cylinder_editor(ui, cylinder),
+        Hittable::Cone(cone) => cone_editor(ui, cone),
+        Hittable::Disk(disk) => disk_editor(ui, disk),
+        Hittable::Torus(torus) => torus_editor(ui, torus),
+        Hittable::Plane(plane) => plane_editor(ui, plane),
+        Hittable::Heightfield(heightfield) => heightfield_editor(ui, heightfield),
+        Hittable::Transform(transform) => transform_editor(ui, transform),
+        Hittable::Rect(_) | Hittable::Sdf(_) | Hittable::Translate(_) => {
+            position_editor(ui, hittable)
+        }
+        Hittable::RotateY(_) => {
+            ui.label("No editable properties");
+        }
+    }
+}
+
+fn position_editor(ui: &mut Ui, hittable: &mut impl HittableTrait) {
+    let mut position = hittable.get_position();
+    ui.label("Position: ");
+    if point3_editor(ui, &mut position).changed() {
+        hittable.set_position(position);
+    }
+    ui.end_row();
+}
 
 fn sphere_editor(ui: &mut Ui, sphere: &mut Sphere) {
     ui.label("Center: ");
     point3_editor(ui, &mut sphere.center);
+    ui.end_row();
+
+    length_editor(ui, "Radius: ", &mut sphere.radius);
+}
+
+fn moving_sphere_editor(ui: &mut Ui, sphere: &mut MovingSphere) {
+    ui.label("Start Center: ");
+    point3_editor(ui, &mut sphere.center0);
+    ui.end_row();
+
+    ui.label("End Center: ");
+    point3_editor(ui, &mut sphere.center1);
+    ui.end_row();
+
+    time_range_editor(ui, &mut sphere.time0, &mut sphere.time1);
+    length_editor(ui, "Radius: ", &mut sphere.radius);
+}
+
+fn time_range_editor(ui: &mut Ui, time0: &mut f64, time1: &mut f64) {
+    ui.label("Time: ");
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(time0).speed(0.01).prefix("Start: "));
+        ui.add(egui::DragValue::new(time1).speed(0.01).prefix("End: "));
+    });
+    ui.end_row();
+}
+
+fn quad_editor(ui: &mut Ui, quad: &mut Quad) {
+    ui.label("Corner: ");
+    point3_editor(ui, &mut quad.corner);
+    ui.end_row();
+
+    vector_editor(ui, "Edge U: ", &mut quad.u, 0.5);
+    vector_editor(ui, "Edge V: ", &mut quad.v, 0.5);
+}
+
+fn cuboid_editor(ui: &mut Ui, cuboid: &mut Cuboid) {
+    position_editor(ui, cuboid);
+
+    let mut size = cuboid.size();
+    if vector_editor(ui, "Size: ", &mut size, 0.5).changed() {
+        cuboid.set_size(size);
+    }
+}
+
+fn cylinder_editor(ui: &mut Ui, cylinder: &mut Cylinder) {
+    ui.label("Center: ");
+    point3_editor(ui, &mut cylinder.center);
+    ui.end_row();
+
+    direction_editor(ui, "Axis: ", &mut cylinder.axis);
+    length_editor(ui, "Radius: ", &mut cylinder.radius);
+    length_editor(ui, "Height: ", &mut cylinder.height);
+
+    ui.label("Capped: ");
+    ui.checkbox(&mut cylinder.capped, "");
+    ui.end_row();
+}
+
+fn cone_editor(ui: &mut Ui, cone: &mut Cone) {
+    ui.label("Center: ");
+    point3_editor(ui, &mut cone.center);
+    ui.end_row();
+
+    direction_editor(ui, "Axis: ", &mut cone.axis);
+    length_editor(ui, "Radius: ", &mut cone.radius);
+    length_editor(ui, "Height: ", &mut cone.height);
+
+    ui.label("Capped: ");
+    ui.checkbox(&mut cone.capped, "");
+    ui.end_row();
+}
+
+fn disk_editor(ui: &mut Ui, disk: &mut Disk) {
+    ui.label("Center: ");
+    point3_editor(ui, &mut disk.center);
+    ui.end_row();
+
+    direction_editor(ui, "Normal: ", &mut disk.normal);
+    length_editor(ui, "Radius: ", &mut disk.radius);
+    length_editor(ui, "Inner Radius: ", &mut disk.inner_radius);
+}
+
+fn torus_editor(ui: &mut Ui, torus: &mut Torus) {
+    ui.label("Center: ");
+    point3_editor(ui, &mut torus.center);
+    ui.end_row();
+
+    direction_editor(ui, "Axis: ", &mut torus.axis);
+    length_editor(ui, "Major Radius: ", &mut torus.major_radius);
+    length_editor(ui, "Minor Radius: ", &mut torus.minor_radius);
+}
+
+fn plane_editor(ui: &mut Ui, plane: &mut Plane) {
+    ui.label("Point: ");
+    point3_editor(ui, &mut plane.point);
+    ui.end_row();
+
+    direction_editor(ui, "Normal: ", &mut plane.normal);
+    length_editor(ui, "UV Scale: ", &mut plane.uv_scale);
+}
+
+fn heightfield_editor(ui: &mut Ui, heightfield: &mut Heightfield) {
+    ui.label("Corner: ");
+    point3_editor(ui, &mut heightfield.corner);
+    ui.end_row();
+
+    vector_editor(ui, "Size: ", &mut heightfield.size, 0.5);
+    // Only the height may be negative, to hang the terrain downwards
+    heightfield.size.x = heightfield.size.x.max(0.001);
+    heightfield.size.z = heightfield.size.z.max(0.001);
+}
+
+fn keyframe_editor(ui: &mut Ui, keyframe: &mut Keyframe) {
+    vector_editor(ui, "Translation: ", &mut keyframe.translation, 0.5);
+    vector_editor(ui, "Rotation: ", &mut keyframe.rotation, 0.5);
+
+    ui.label("Scale: ");
+    ui.add(
+        egui::DragValue::new(&mut keyframe.scale)
+            .speed(0.01)
+            .clamp_range(0.001..=f64::INFINITY),
+    );
+    ui.end_row();
+}
+
+fn transform_editor(ui: &mut Ui, transform: &mut Transform) {
+    ui.label(egui::RichText::new("Start").strong());
+    ui.end_row();
+    keyframe_editor(ui, &mut transform.start);
+
+    ui.label(egui::RichText::new("End").strong());
+    ui.end_row();
+    keyframe_editor(ui, &mut transform.end);
 
-    ui.label("Radius: ");
-    ui.add(egui::DragValue::new(&mut sphere.radius).speed(0.5));
+    time_range_editor(ui, &mut transform.time0, &mut transform.time1);
 }
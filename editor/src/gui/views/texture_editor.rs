@@ -1,76 +1,193 @@
 use egui::{color_picker::show_color, Color32, Ui};
 
-use crate::gui::image_storage::IMAGE_STORAGE;
+use crate::gui::{image_storage::IMAGE_STORAGE, logger::LOGGER};
 use ray::{
-    render::texture::{Image, Texture},
+    render::{
+        filtering::{Filter, Sampler, WrapMode},
+        procedural::{
+            Checker, ColourRamp, Gradient, GradientKind, Marble, Noise, NoiseKind, RampStop,
+            TextureSpace, Wood,
+        },
+        sky::Sky,
+        texture::{ColourSpace, HdrImage, Image, TexCoord, Texture},
+        Colour, Vector3,
+    },
     vec_repo::{Id, VecRepo},
 };
 
-use super::{grid, View};
+use super::{
+    asset_label, grid,
+    texture_nodes::{default_nodes, is_node, node_editor, node_name, texture_tree},
+    View,
+};
 use crate::io;
 
+#[derive(Default, PartialEq)]
+enum TextureKind {
+    #[default]
+    Colour,
+    Image,
+    Sky,
+    Pattern,
+    Node,
+}
+
 #[derive(Default)]
 struct TextureEditorState {
-    edited_id: Option<usize>,
-    choosing_image: bool,
+    edited_id: Option<Id<Texture>>,
+    edited_name: String,
+    kind: TextureKind,
     edited_rgb: [f32; 3],
-    edited_image: Option<Image>,
+    /// Picked image, as a texture since it may be either 8-bit or HDR.
+    edited_image: Option<Texture>,
+    edited_sky: Sky,
+    edited_pattern: Option<Texture>,
+    edited_node: Option<Texture>,
 }
 
 impl TextureEditorState {
-    fn setup(&mut self, id: usize, tex: &Texture) {
+    fn setup(&mut self, id: Id<Texture>, name: Option<&str>, tex: &Texture) {
         self.edited_id = Some(id);
+        self.edited_name = name.unwrap_or_default().to_owned();
         match tex {
             Texture::Colour(c) => {
-                self.choosing_image = false;
+                self.kind = TextureKind::Colour;
                 self.edited_rgb = [c.x, c.y, c.z];
                 self.edited_image = None;
             }
-            Texture::Image(img) => {
-                self.choosing_image = true;
-                self.edited_image = Some(img.clone());
+            Texture::Image(_) | Texture::HdrImage(_) => {
+                self.kind = TextureKind::Image;
+                self.edited_image = Some(tex.clone());
+            }
+            Texture::Sky(sky) => {
+                self.kind = TextureKind::Sky;
+                self.edited_sky = sky.clone();
+            }
+            _ if is_node(tex) => {
+                self.kind = TextureKind::Node;
+                self.edited_node = Some(tex.clone());
+            }
+            _ => {
+                self.kind = TextureKind::Pattern;
+                self.edited_pattern = Some(tex.clone());
             }
         }
     }
 
-    fn show_editor(&mut self, ui: &mut Ui, tex: &mut Texture) {
+    /// Returns the texture and name to store once saved.
+    fn show_editor(
+        &mut self,
+        ui: &mut Ui,
+        textures: &VecRepo<Texture>,
+    ) -> Option<(Texture, String)> {
+        let edited = self.edited_id?;
+        let mut saved = None;
+        ui.horizontal(|ui| {
+            ui.label("Name: ");
+            ui.text_edit_singleline(&mut self.edited_name);
+        });
         ui.vertical(|ui| {
             ui.style_mut().wrap = Some(false);
-            ui.radio_value(&mut self.choosing_image, false, "Colour");
-            ui.radio_value(&mut self.choosing_image, true, "Image");
+            ui.radio_value(&mut self.kind, TextureKind::Colour, "Colour");
+            ui.radio_value(&mut self.kind, TextureKind::Image, "Image");
+            ui.radio_value(&mut self.kind, TextureKind::Sky, "Sky");
+            ui.radio_value(&mut self.kind, TextureKind::Pattern, "Pattern");
+            ui.radio_value(&mut self.kind, TextureKind::Node, "Node");
         });
-        if self.choosing_image {
-            if let Some(ref edited_image) = self.edited_image {
-                image_preview(ui, edited_image, 100.0);
-            }
-            if ui.button("Open file...").clicked() {
-                self.edited_image = rfd::FileDialog::new()
-                    .pick_file()
-                    .and_then(|path| io::try_open(&path).ok())
-                    .map(|image| {
-                        let image = Image::new(image);
-                        IMAGE_STORAGE.add_retained(&image);
-                        image
+        match self.kind {
+            TextureKind::Colour => {
+                ui.color_edit_button_rgb(&mut self.edited_rgb);
+            }
+            TextureKind::Image => {
+                if let Some(ref edited_image) = self.edited_image {
+                    image_preview(ui, edited_image, textures, 100.0);
+                }
+                if ui.button("Open file...").clicked() {
+                    self.edited_image = rfd::FileDialog::new()
+                        .pick_file()
+                        .and_then(|path| open_texture(&path));
+                }
+                match self.edited_image {
+                    Some(Texture::Image(ref mut image)) => {
+                        grid(ui, "sampler", 2, false).show(ui, |ui| {
+                            let mut space = image.colour_space();
+                            colour_space_editor(ui, &mut space);
+                            image.set_colour_space(space);
+                            sampler_editor(ui, &mut image.sampler);
+                        });
+                    }
+                    Some(Texture::HdrImage(ref mut image)) => {
+                        grid(ui, "sampler", 2, false).show(ui, |ui| {
+                            let mut space = image.colour_space();
+                            colour_space_editor(ui, &mut space);
+                            image.set_colour_space(space);
+                            sampler_editor(ui, &mut image.sampler);
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            TextureKind::Sky => {
+                grid(ui, "sky", 2, false).show(ui, |ui| sky_editor(ui, &mut self.edited_sky));
+            }
+            TextureKind::Pattern => {
+                let selected = self
+                    .edited_pattern
+                    .as_ref()
+                    .map_or("Choose...", pattern_name);
+                egui::ComboBox::from_id_source(ui.auto_id_with("pattern"))
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for pattern in default_patterns() {
+                            let name = pattern_name(&pattern);
+                            if ui.selectable_label(name == selected, name).clicked() {
+                                self.edited_pattern = Some(pattern);
+                            }
+                        }
                     });
+                if let Some(ref mut pattern) = self.edited_pattern {
+                    image_preview(ui, pattern, textures, 100.0);
+                    grid(ui, "pattern", 2, false).show(ui, |ui| pattern_editor(ui, pattern));
+                }
+            }
+            TextureKind::Node => {
+                let selected = self.edited_node.as_ref().map_or("Choose...", node_name);
+                egui::ComboBox::from_id_source(ui.auto_id_with("node"))
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for node in default_nodes() {
+                            let name = node_name(&node);
+                            if ui.selectable_label(name == selected, name).clicked() {
+                                self.edited_node = Some(node);
+                            }
+                        }
+                    });
+                if let Some(ref mut node) = self.edited_node {
+                    image_preview(ui, node, textures, 100.0);
+                    grid(ui, "node", 2, false)
+                        .show(ui, |ui| node_editor(ui, node, textures, edited));
+                }
             }
-        } else {
-            ui.color_edit_button_rgb(&mut self.edited_rgb);
         }
         ui.vertical(|ui| {
             ui.style_mut().wrap = Some(false);
             let save = ui.button("Save");
             let cancel = ui.button("Cancel");
             if save.clicked() {
-                if !self.choosing_image {
-                    *tex = Texture::Colour(self.edited_rgb.into());
-                } else if let Some(ref img) = self.edited_image {
-                    *tex = img.clone().into();
+                saved = match self.kind {
+                    TextureKind::Colour => Some(Texture::Colour(self.edited_rgb.into())),
+                    TextureKind::Image => self.edited_image.clone(),
+                    TextureKind::Sky => Some(self.edited_sky.clone().into()),
+                    TextureKind::Pattern => self.edited_pattern.clone(),
+                    TextureKind::Node => self.edited_node.clone(),
                 }
+                .map(|tex| (tex, self.edited_name.clone()));
             }
             if cancel.clicked() || save.clicked() {
                 self.edited_id = None;
             }
         });
+        saved
     }
 }
 
@@ -96,18 +213,15 @@ impl TextureEditor {
 
     pub fn texture_picker(&self, ui: &mut Ui, tex_id: &mut Id<Texture>) {
         egui::ComboBox::from_label("")
-            .selected_text(format!("Texture {}", tex_id))
+            .selected_text(asset_label(&self.textures, "Texture", *tex_id))
             .show_ui(ui, |ui| {
                 ui.selectable_value(tex_id, Id::default(), "Default");
-                for (option, _tex) in self.textures.iter().enumerate().skip(1) {
-                    ui.selectable_value(
-                        tex_id,
-                        Id::new(option as u32),
-                        format!("Texture {}", option),
-                    );
+                for id in self.textures.ids().skip(1) {
+                    let label = asset_label(&self.textures, "Texture", id);
+                    ui.selectable_value(tex_id, id, label);
                 }
             });
-        texture_preview(ui, self.textures.get(*tex_id), false);
+        texture_preview(ui, self.textures.get(*tex_id), &self.textures, false);
     }
 }
 
@@ -121,14 +235,14 @@ impl View for TextureEditor {
             editor_state,
             textures,
         } = self;
-        let mut tex_iter = textures.iter_mut().enumerate().peekable();
+        let mut tex_iter = textures.iter_with_ids().peekable();
         grid(ui, "Textures1", 4, true).show(ui, |ui| {
             while let Some((id, tex)) = tex_iter.peek() {
-                ui.label(format!("Texture {}", id));
-                texture_preview(ui, tex, true);
+                ui.label(asset_label(textures, "Texture", *id));
+                texture_preview(ui, tex, textures, true);
                 if editor_state.edited_id == None {
                     if ui.button("Edit").clicked() {
-                        editor_state.setup(*id, tex);
+                        editor_state.setup(*id, textures.name(*id), tex);
                     }
                 } else {
                     ui.label("");
@@ -143,17 +257,20 @@ impl View for TextureEditor {
             }
         });
 
-        if let Some((_id, tex)) = tex_iter.next() {
-            editor_state.show_editor(ui, tex);
-        }
+        let saved = match tex_iter.next() {
+            Some((id, _tex)) => editor_state
+                .show_editor(ui, textures)
+                .map(|(tex, name)| (id, tex, name)),
+            None => None,
+        };
 
         grid(ui, "Textures2", 4, true).show(ui, |ui| {
             for (id, tex) in tex_iter {
-                ui.label(format!("Texture {}", id));
-                texture_preview(ui, tex, true);
-                if self.editor_state.edited_id == None {
+                ui.label(asset_label(textures, "Texture", id));
+                texture_preview(ui, tex, textures, true);
+                if editor_state.edited_id.is_none() {
                     if ui.button("Edit").clicked() {
-                        self.editor_state.setup(id, tex);
+                        editor_state.setup(id, textures.name(id), tex);
                     }
                 } else {
                     ui.label("");
@@ -161,10 +278,19 @@ impl View for TextureEditor {
                 ui.end_row();
             }
         });
+
+        if let Some((id, tex, name)) = saved {
+            if let Some(stored) = textures.get_mut(id) {
+                *stored = tex;
+            }
+            if !textures.set_name(id, &name) {
+                LOGGER.log(format!("Another texture is already named {}", name));
+            }
+        }
     }
 }
 
-fn texture_preview(ui: &mut Ui, tex: &Texture, show_type: bool) {
+fn texture_preview(ui: &mut Ui, tex: &Texture, textures: &VecRepo<Texture>, show_type: bool) {
     match tex {
         Texture::Colour(c) => {
             if show_type {
@@ -173,18 +299,291 @@ fn texture_preview(ui: &mut Ui, tex: &Texture, show_type: bool) {
             let colour: Color32 = egui::Rgba::from_rgb(c.x, c.y, c.z).into();
             show_color(ui, colour, ui.available_size_before_wrap());
         }
-        Texture::Image(img) => {
+        Texture::Image(_) | Texture::HdrImage(_) => {
             if show_type {
-                ui.label("Image");
+                ui.label(match tex {
+                    Texture::HdrImage(_) => "HDR Image",
+                    _ => "Image",
+                });
             }
             ui.label("example.png")
-                .on_hover_ui(|ui| image_preview(ui, img, 250.0));
+                .on_hover_ui(|ui| image_preview(ui, tex, textures, 250.0));
+        }
+        Texture::Sky(sky) => {
+            if show_type {
+                ui.label("Sky");
+            }
+            let c = sky.radiance(Vector3::unit_y());
+            let colour: Color32 = egui::Rgba::from_rgb(c.x, c.y, c.z).into();
+            show_color(ui, colour, ui.available_size_before_wrap());
         }
+        _ if is_node(tex) => {
+            if show_type {
+                ui.label("Node");
+            }
+            ui.vertical(|ui| {
+                ui.label(node_name(tex))
+                    .on_hover_ui(|ui| image_preview(ui, tex, textures, 250.0));
+                if show_type {
+                    texture_tree(ui, tex, textures);
+                }
+            });
+        }
+        _ => {
+            if show_type {
+                ui.label("Pattern");
+            }
+            ui.label(pattern_name(tex))
+                .on_hover_ui(|ui| image_preview(ui, tex, textures, 250.0));
+        }
+    }
+}
+
+pub(super) fn texture_name(tex: &Texture) -> &'static str {
+    match tex {
+        Texture::Colour(_) => "Colour",
+        Texture::Image(_) => "Image",
+        Texture::HdrImage(_) => "HDR Image",
+        Texture::Sky(_) => "Sky",
+        _ if is_node(tex) => node_name(tex),
+        _ => pattern_name(tex),
     }
 }
 
-fn image_preview(ui: &mut Ui, img: &Image, max_size: f32) {
-    IMAGE_STORAGE.with_retained(img, |image| {
+fn default_patterns() -> [Texture; 5] {
+    [
+        Checker::default().into(),
+        Noise::default().into(),
+        Marble::default().into(),
+        Wood::default().into(),
+        Gradient::default().into(),
+    ]
+}
+
+fn pattern_name(tex: &Texture) -> &'static str {
+    match tex {
+        Texture::Checker(_) => "Checker",
+        Texture::Noise(_) => "Noise",
+        Texture::Marble(_) => "Marble",
+        Texture::Wood(_) => "Wood",
+        Texture::Gradient(_) => "Gradient",
+        _ => "",
+    }
+}
+
+fn pattern_editor(ui: &mut Ui, tex: &mut Texture) {
+    match tex {
+        Texture::Checker(checker) => {
+            space_editor(ui, &mut checker.space, &mut checker.scale);
+            colour_editor(ui, "Even: ", &mut checker.even);
+            colour_editor(ui, "Odd: ", &mut checker.odd);
+        }
+        Texture::Noise(noise) => {
+            ui.label("Kind: ");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut noise.kind, NoiseKind::Perlin, "Perlin");
+                ui.radio_value(&mut noise.kind, NoiseKind::Worley, "Worley");
+            });
+            ui.end_row();
+            space_editor(ui, &mut noise.space, &mut noise.scale);
+            octaves_editor(ui, &mut noise.octaves);
+            ui.label("Turbulence: ");
+            ui.checkbox(&mut noise.turbulence, "");
+            ui.end_row();
+            colour_editor(ui, "Low: ", &mut noise.low);
+            colour_editor(ui, "High: ", &mut noise.high);
+        }
+        Texture::Marble(marble) => {
+            space_editor(ui, &mut marble.space, &mut marble.scale);
+            distortion_editor(ui, &mut marble.distortion);
+            octaves_editor(ui, &mut marble.octaves);
+            colour_editor(ui, "Base: ", &mut marble.base);
+            colour_editor(ui, "Vein: ", &mut marble.vein);
+        }
+        Texture::Wood(wood) => {
+            space_editor(ui, &mut wood.space, &mut wood.scale);
+            distortion_editor(ui, &mut wood.distortion);
+            colour_editor(ui, "Light: ", &mut wood.light);
+            colour_editor(ui, "Dark: ", &mut wood.dark);
+        }
+        Texture::Gradient(gradient) => {
+            ui.label("Kind: ");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut gradient.kind, GradientKind::Horizontal, "Horizontal");
+                ui.radio_value(&mut gradient.kind, GradientKind::Vertical, "Vertical");
+                ui.radio_value(&mut gradient.kind, GradientKind::Radial, "Radial");
+            });
+            ui.end_row();
+            ramp_editor(ui, &mut gradient.ramp);
+        }
+        _ => {}
+    }
+}
+
+fn space_editor(ui: &mut Ui, space: &mut TextureSpace, scale: &mut f64) {
+    ui.label("Space: ");
+    ui.horizontal(|ui| {
+        ui.radio_value(space, TextureSpace::Uv, "UV");
+        ui.radio_value(space, TextureSpace::World, "World");
+    });
+    ui.end_row();
+
+    ui.label("Scale: ");
+    ui.add(
+        egui::DragValue::new(scale)
+            .speed(0.1)
+            .clamp_range(0.0..=f64::INFINITY),
+    );
+    ui.end_row();
+}
+
+fn distortion_editor(ui: &mut Ui, distortion: &mut f64) {
+    ui.label("Distortion: ");
+    ui.add(
+        egui::DragValue::new(distortion)
+            .speed(0.05)
+            .clamp_range(0.0..=f64::INFINITY),
+    );
+    ui.end_row();
+}
+
+fn octaves_editor(ui: &mut Ui, octaves: &mut u32) {
+    ui.label("Octaves: ");
+    ui.add(egui::Slider::new(octaves, 1..=8));
+    ui.end_row();
+}
+
+fn colour_editor(ui: &mut Ui, label: &str, colour: &mut Colour) {
+    ui.label(label);
+    ui.color_edit_button_rgb(colour.as_mut());
+    ui.end_row();
+}
+
+pub(super) fn ramp_editor(ui: &mut Ui, ramp: &mut ColourRamp) {
+    let mut removed = None;
+    for (idx, stop) in ramp.stops.iter_mut().enumerate() {
+        ui.label(format!("Stop {}: ", idx));
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut stop.position)
+                    .speed(0.01)
+                    .clamp_range(0.0..=1.0),
+            );
+            ui.color_edit_button_rgb(stop.colour.as_mut());
+            if ui.small_button("x").clicked() {
+                removed = Some(idx);
+            }
+        });
+        ui.end_row();
+    }
+    if let Some(idx) = removed {
+        ramp.stops.remove(idx);
+    }
+    ramp.stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+    if ui.button("Add Stop").clicked() {
+        let colour = ramp.colour_at(0.5);
+        ramp.stops.push(RampStop {
+            position: 0.5,
+            colour,
+        });
+    }
+    ui.end_row();
+}
+
+/// Pattern laid out over the uv square, gamma corrected for display.
+fn bake_pattern(tex: &Texture, textures: &VecRepo<Texture>) -> Image {
+    const SIZE: u32 = 64;
+    Image::new(image::RgbImage::from_fn(SIZE, SIZE, |i, j| {
+        let at = TexCoord::uv(
+            (i as f64 + 0.5) / SIZE as f64,
+            (j as f64 + 0.5) / SIZE as f64,
+        );
+        let c = tex.colour_at(at, textures);
+        image::Rgb([c.x, c.y, c.z].map(|c| (c.clamp(0.0, 1.0).sqrt() * 255.0) as u8))
+    }))
+}
+
+fn colour_space_editor(ui: &mut Ui, space: &mut ColourSpace) {
+    ui.label("Colour Space: ");
+    ui.horizontal(|ui| {
+        ui.radio_value(space, ColourSpace::Srgb, "sRGB");
+        ui.radio_value(space, ColourSpace::Linear, "Linear");
+        ui.radio_value(space, ColourSpace::Raw, "Raw Data");
+    });
+    ui.end_row();
+}
+
+fn sampler_editor(ui: &mut Ui, sampler: &mut Sampler) {
+    ui.label("Wrap: ");
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut sampler.wrap, WrapMode::Clamp, "Clamp");
+        ui.radio_value(&mut sampler.wrap, WrapMode::Repeat, "Repeat");
+        ui.radio_value(&mut sampler.wrap, WrapMode::Mirror, "Mirror");
+    });
+    ui.end_row();
+
+    ui.label("Filter: ");
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut sampler.filter, Filter::Nearest, "Nearest");
+        ui.radio_value(&mut sampler.filter, Filter::Bilinear, "Bilinear");
+        ui.radio_value(&mut sampler.filter, Filter::Bicubic, "Bicubic");
+        ui.radio_value(&mut sampler.filter, Filter::Trilinear, "Trilinear");
+    });
+    ui.end_row();
+}
+
+fn sky_editor(ui: &mut Ui, sky: &mut Sky) {
+    ui.label("Elevation: ");
+    ui.add(egui::Slider::new(&mut sky.elevation, -10.0..=90.0).suffix("°"));
+    ui.end_row();
+
+    ui.label("Azimuth: ");
+    ui.add(egui::Slider::new(&mut sky.azimuth, -180.0..=180.0).suffix("°"));
+    ui.end_row();
+
+    ui.label("Turbidity: ");
+    ui.add(egui::Slider::new(&mut sky.turbidity, 2.0..=10.0));
+    ui.end_row();
+
+    ui.label("Ground: ");
+    ui.color_edit_button_rgb(sky.ground_albedo.as_mut());
+    ui.end_row();
+
+    ui.label("Sun Light: ");
+    ui.checkbox(&mut sky.sun, "");
+    ui.end_row();
+}
+
+fn open_texture(path: &std::path::PathBuf) -> Option<Texture> {
+    use image::DynamicImage::*;
+    match io::try_open(path).ok()? {
+        image @ (ImageRgb32F(_) | ImageRgba32F(_)) => {
+            let image = HdrImage::new(image.into_rgb32f());
+            IMAGE_STORAGE.add_retained_hdr(&image);
+            Some(image.into())
+        }
+        // Widened to floats, but still holding sRGB values
+        image @ (ImageLuma16(_) | ImageLumaA16(_) | ImageRgb16(_) | ImageRgba16(_)) => {
+            let mut image = HdrImage::new(image.into_rgb32f());
+            image.set_colour_space(ColourSpace::Srgb);
+            IMAGE_STORAGE.add_retained_hdr(&image);
+            Some(image.into())
+        }
+        image @ (ImageLumaA8(_) | ImageRgba8(_)) => {
+            let image = Image::from_rgba(image.into_rgba8());
+            IMAGE_STORAGE.add_retained(&image);
+            Some(image.into())
+        }
+        image => {
+            let image = Image::new(image.into_rgb8());
+            IMAGE_STORAGE.add_retained(&image);
+            Some(image.into())
+        }
+    }
+}
+
+fn image_preview(ui: &mut Ui, tex: &Texture, textures: &VecRepo<Texture>, max_size: f32) {
+    let show = |image: &egui_extras::RetainedImage| {
         let [width, height] = image.size();
         let (width, height) = (width as f32, height as f32);
         if width >= height {
@@ -192,5 +591,11 @@ fn image_preview(ui: &mut Ui, img: &Image, max_size: f32) {
         } else {
             image.show_scaled(ui, max_size / height);
         }
-    });
+    };
+    match tex {
+        Texture::Image(img) => IMAGE_STORAGE.with_retained(img, show),
+        Texture::HdrImage(img) => IMAGE_STORAGE.with_retained_hdr(img, show),
+        Texture::Colour(_) | Texture::Sky(_) => {}
+        _ => IMAGE_STORAGE.with_retained(&bake_pattern(tex, textures), show),
+    }
 }
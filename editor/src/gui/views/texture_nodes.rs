@@ -0,0 +1,198 @@
+use egui::Ui;
+
+use ray::{
+    render::{
+        procedural::ColourRamp,
+        texture::Texture,
+        texture_node::{Add, HueSaturation, Invert, Lerp, Multiply, Ramp, UvTransform},
+    },
+    vec_repo::{Id, VecRepo},
+};
+
+use super::{
+    asset_label,
+    texture_editor::{ramp_editor, texture_name},
+};
+
+/// Deeper than any sensible graph, so a cycle in a loaded file can't hang the editor.
+const MAX_DEPTH: usize = 16;
+
+pub(super) fn default_nodes() -> [Texture; 7] {
+    let input = Id::default();
+    [
+        Multiply { a: input, b: input }.into(),
+        Add { a: input, b: input }.into(),
+        Lerp {
+            a: input,
+            b: input,
+            mask: input,
+        }
+        .into(),
+        Ramp {
+            input,
+            ramp: ColourRamp::default(),
+        }
+        .into(),
+        HueSaturation::new(input).into(),
+        Invert { input }.into(),
+        UvTransform::new(input).into(),
+    ]
+}
+
+pub(super) fn is_node(tex: &Texture) -> bool {
+    !node_inputs(tex).is_empty()
+}
+
+pub(super) fn node_name(tex: &Texture) -> &'static str {
+    match tex {
+        Texture::Multiply(_) => "Multiply",
+        Texture::Add(_) => "Add",
+        Texture::Lerp(_) => "Lerp",
+        Texture::Ramp(_) => "Colour Ramp",
+        Texture::HueSaturation(_) => "Hue/Saturation",
+        Texture::Invert(_) => "Invert",
+        Texture::UvTransform(_) => "UV Transform",
+        _ => "",
+    }
+}
+
+/// Textures `tex` reads, with what it uses them for.
+fn node_inputs(tex: &Texture) -> Vec<(&'static str, Id<Texture>)> {
+    match tex {
+        Texture::Multiply(node) => vec![("A", node.a), ("B", node.b)],
+        Texture::Add(node) => vec![("A", node.a), ("B", node.b)],
+        Texture::Lerp(node) => vec![("A", node.a), ("B", node.b), ("Mask", node.mask)],
+        Texture::Ramp(node) => vec![("Input", node.input)],
+        Texture::HueSaturation(node) => vec![("Input", node.input)],
+        Texture::Invert(node) => vec![("Input", node.input)],
+        Texture::UvTransform(node) => vec![("Input", node.input)],
+        _ => Vec::new(),
+    }
+}
+
+/// Whether `from` is `target` or reads it through its inputs.
+fn reads(
+    textures: &VecRepo<Texture>,
+    from: Id<Texture>,
+    target: Id<Texture>,
+    depth: usize,
+) -> bool {
+    from == target
+        || (depth < MAX_DEPTH
+            && node_inputs(textures.get(from))
+                .into_iter()
+                .any(|(_, input)| reads(textures, input, target, depth + 1)))
+}
+
+/// Inputs of `tex` and theirs in turn, as collapsible branches.
+pub(super) fn texture_tree(ui: &mut Ui, tex: &Texture, textures: &VecRepo<Texture>) {
+    tree_branch(ui, tex, textures, 0);
+}
+
+fn tree_branch(ui: &mut Ui, tex: &Texture, textures: &VecRepo<Texture>, depth: usize) {
+    for (label, id) in node_inputs(tex) {
+        let input = textures.get(id);
+        let text = format!(
+            "{}: {} ({})",
+            label,
+            asset_label(textures, "Texture", id),
+            texture_name(input)
+        );
+        if is_node(input) && depth < MAX_DEPTH {
+            egui::CollapsingHeader::new(text)
+                .id_source(ui.auto_id_with((label, depth)))
+                .show(ui, |ui| tree_branch(ui, input, textures, depth + 1));
+        } else {
+            ui.label(text);
+        }
+    }
+}
+
+/// Picks `input` among the textures that don't read `edited`, so no cycle can be made.
+fn input_picker(
+    ui: &mut Ui,
+    label: &str,
+    input: &mut Id<Texture>,
+    textures: &VecRepo<Texture>,
+    edited: Id<Texture>,
+) {
+    ui.label(label);
+    egui::ComboBox::from_id_source(ui.auto_id_with(label))
+        .selected_text(asset_label(textures, "Texture", *input))
+        .show_ui(ui, |ui| {
+            for (id, tex) in textures.iter_with_ids() {
+                if !reads(textures, id, edited, 0) {
+                    let text = format!(
+                        "{} ({})",
+                        asset_label(textures, "Texture", id),
+                        texture_name(tex)
+                    );
+                    ui.selectable_value(input, id, text);
+                }
+            }
+        });
+    ui.end_row();
+}
+
+pub(super) fn node_editor(
+    ui: &mut Ui,
+    tex: &mut Texture,
+    textures: &VecRepo<Texture>,
+    edited: Id<Texture>,
+) {
+    let picker = |ui: &mut Ui, label, input: &mut Id<Texture>| {
+        input_picker(ui, label, input, textures, edited)
+    };
+    match tex {
+        Texture::Multiply(node) => {
+            picker(ui, "A: ", &mut node.a);
+            picker(ui, "B: ", &mut node.b);
+        }
+        Texture::Add(node) => {
+            picker(ui, "A: ", &mut node.a);
+            picker(ui, "B: ", &mut node.b);
+        }
+        Texture::Lerp(node) => {
+            picker(ui, "A: ", &mut node.a);
+            picker(ui, "B: ", &mut node.b);
+            picker(ui, "Mask: ", &mut node.mask);
+        }
+        Texture::Ramp(node) => {
+            picker(ui, "Input: ", &mut node.input);
+            ramp_editor(ui, &mut node.ramp);
+        }
+        Texture::HueSaturation(node) => {
+            picker(ui, "Input: ", &mut node.input);
+            ui.label("Hue: ");
+            ui.add(egui::Slider::new(&mut node.hue, -180.0..=180.0).suffix("°"));
+            ui.end_row();
+            ui.label("Saturation: ");
+            ui.add(egui::Slider::new(&mut node.saturation, 0.0..=2.0));
+            ui.end_row();
+            ui.label("Value: ");
+            ui.add(egui::Slider::new(&mut node.value, 0.0..=2.0));
+            ui.end_row();
+        }
+        Texture::Invert(node) => {
+            picker(ui, "Input: ", &mut node.input);
+        }
+        Texture::UvTransform(node) => {
+            picker(ui, "Input: ", &mut node.input);
+            pair_editor(ui, "Scale: ", &mut node.scale);
+            ui.label("Rotation: ");
+            ui.add(egui::Slider::new(&mut node.rotation, -180.0..=180.0).suffix("°"));
+            ui.end_row();
+            pair_editor(ui, "Offset: ", &mut node.offset);
+        }
+        _ => {}
+    }
+}
+
+fn pair_editor(ui: &mut Ui, label: &str, (u, v): &mut (f64, f64)) {
+    ui.label(label);
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(u).speed(0.01).prefix("U: "));
+        ui.add(egui::DragValue::new(v).speed(0.01).prefix("V: "));
+    });
+    ui.end_row();
+}
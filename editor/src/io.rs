@@ -1,3 +1,60 @@
-pub fn try_open(filename: &std::path::PathBuf) -> image::ImageResult<image::RgbImage> {
-    Ok(image::io::Reader::open(filename)?.decode()?.into_rgb8())
+/// Decoded at the precision it was stored with, so 16-bit and float files aren't cut down to 8 bits.
+pub fn try_open(filename: &std::path::PathBuf) -> image::ImageResult<image::DynamicImage> {
+    image::io::Reader::open(filename)?.decode()
+}
+
+/// Reads a scene file of this or an older format version.
+pub fn read_scene(filename: &std::path::Path) -> anyhow::Result<ray::render::scene::Scene> {
+    ray::render::scene_file::from_bytes(&std::fs::read(filename)?)
+}
+
+pub fn write_scene(
+    filename: &std::path::Path,
+    scene: &ray::render::scene::Scene,
+) -> anyhow::Result<()> {
+    std::fs::write(filename, ray::render::scene_file::to_bytes(scene)?)?;
+    Ok(())
+}
+
+/// Writes a rendered pass as OpenEXR when `filename` ends in `.exr`, otherwise as PNG.
+/// `srgb` tells whether `image` is gamma encoded, like the colour passes, which EXR
+/// stores linear. `alpha` is added as a fourth channel.
+pub fn save_render(
+    filename: &std::path::Path,
+    image: &image::Rgb32FImage,
+    alpha: Option<&ray::render::AlphaImage>,
+    srgb: bool,
+) -> image::ImageResult<()> {
+    use image::{DynamicImage, ImageFormat, Rgba, Rgba32FImage};
+    use ray::render::{texture::ColourSpace, Colour};
+
+    let exr = filename
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exr"));
+    let rgba = Rgba32FImage::from_fn(image.width(), image.height(), |i, j| {
+        let mut c = Colour::from(image.get_pixel(i, j).0);
+        let a = alpha.map_or(1.0, |alpha| alpha.get_pixel(i, j).0[0]);
+        if exr {
+            // EXR colours are linear and premultiplied by alpha
+            if srgb {
+                c = ColourSpace::Srgb.decode(c);
+            }
+            c *= a;
+        }
+        Rgba([c.x, c.y, c.z, a])
+    });
+    let mut output = DynamicImage::ImageRgba32F(rgba);
+    if alpha.is_none() {
+        output = DynamicImage::ImageRgb32F(output.into_rgb32f());
+    }
+    if exr {
+        output.save_with_format(filename, ImageFormat::OpenExr)
+    } else {
+        let output = match alpha {
+            Some(_) => DynamicImage::ImageRgba8(output.into_rgba8()),
+            None => DynamicImage::ImageRgb8(output.into_rgb8()),
+        };
+        output.save_with_format(filename, ImageFormat::Png)
+    }
 }
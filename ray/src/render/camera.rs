@@ -1,4 +1,6 @@
-use crate::render::{random_vec_in_disc, Point3, Ray, Vector3};
+use crate::render::{
+    random_f64, random_vec_in_disc, spectrum::sample_wavelength, Point3, Ray, Vector3,
+};
 use cgmath::{point3, vec3, InnerSpace};
 use serde::{Deserialize, Serialize};
 
@@ -14,16 +16,23 @@ pub struct Camera {
     v: Vector3,
 
     lens_radius: f64,
+    /// Angle between neighbouring pixels, zero when the resolution isn't known.
+    pixel_spread: f64,
     pub settings: CameraSettings,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CameraSettings {
     pub look_from: Point3,
     pub look_at: Point3,
     pub up_vec: Vector3,
     pub fov: f64,
     pub aperture: f64,
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    /// Trace a single wavelength per path, needed for dispersion.
+    pub spectral: bool,
 }
 
 impl Default for CameraSettings {
@@ -34,6 +43,9 @@ impl Default for CameraSettings {
             up_vec: vec3(0.0, 1.0, 0.0),
             fov: 45.0,
             aperture: 1.0 / 16.0,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            spectral: false,
         }
     }
 }
@@ -67,12 +79,17 @@ impl CameraSettings {
             u,
             v,
             lens_radius,
+            pixel_spread: 0.0,
             settings: self.clone(),
         }
     }
 
     pub fn build_with_dimensions(&self, width: u32, height: u32) -> Camera {
-        self.build_with_aspect_ratio(width as f64 / height as f64)
+        let viewport_height = 2.0 * (self.fov.to_radians() / 2.0).tan();
+        Camera {
+            pixel_spread: viewport_height / height as f64,
+            ..self.build_with_aspect_ratio(width as f64 / height as f64)
+        }
     }
 }
 
@@ -84,9 +101,14 @@ impl Camera {
             - self.origin
             - offset)
             .normalize();
+        let time = random_f64(self.settings.shutter_open, self.settings.shutter_close);
         Ray {
             origin: self.origin + offset,
             direction,
+            time,
+            wavelength: self.settings.spectral.then(sample_wavelength),
+            width: 0.0,
+            spread: self.pixel_spread,
         }
     }
 }
@@ -0,0 +1,225 @@
+use std::f64::consts::PI;
+
+use cgmath::{vec3, EuclideanSpace, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::vec_repo::VecRepo;
+
+use super::{
+    light::{LightSample, SunLight},
+    random_f64,
+    texture::{pixel_at, HdrImage, TexCoord, Texture},
+    Colour, Point3, Vector3,
+};
+
+/// How the background texture is wrapped around the scene, as an equirectangular map.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Environment {
+    /// Turn around the vertical axis, in degrees.
+    pub rotation: f64,
+    pub intensity: f32,
+    /// Hide the background from the camera while it still lights the scene,
+    /// so renders can be composited over something else.
+    #[serde(default)]
+    pub transparent: bool,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            rotation: 0.0,
+            intensity: 1.0,
+            transparent: false,
+        }
+    }
+}
+
+impl Environment {
+    /// Solid textures are looked up on the unit sphere of directions.
+    pub fn colour(
+        &self,
+        texture: &Texture,
+        textures: &VecRepo<Texture>,
+        direction: Vector3,
+    ) -> Colour {
+        let local = rotate(direction, -self.rotation).normalize();
+        let (u, v) = equirect_uv(local);
+        let at = TexCoord {
+            u,
+            v,
+            point: Point3::from_vec(local),
+            footprint: (0.0, 0.0),
+        };
+        texture.colour_at(at, textures) * self.intensity
+    }
+
+    /// Direction picked in proportion to the brightness of HDR image backgrounds,
+    /// which are the ones bright enough to be worth sampling as a light.
+    pub fn sample(&self, texture: &Texture, textures: &VecRepo<Texture>) -> Option<LightSample> {
+        let Texture::HdrImage(image) = texture else {
+            return None;
+        };
+        let (u, v, pdf) = distribution(image).sample()?;
+        let direction = self.direction(u, v);
+        let sin = (PI * v).sin();
+        if sin <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance: f64::INFINITY,
+            radiance: self.colour(texture, textures, direction),
+            pdf: Some(pdf / (2.0 * PI * PI * sin)),
+        })
+    }
+
+    /// Solid angle density of `sample` picking `direction`.
+    pub fn pdf(&self, texture: &Texture, direction: Vector3) -> f64 {
+        let Texture::HdrImage(image) = texture else {
+            return 0.0;
+        };
+        let (u, v) = self.uv(direction);
+        let sin = (PI * v).sin();
+        if sin <= 0.0 {
+            return 0.0;
+        }
+        distribution(image).pdf(u, v) / (2.0 * PI * PI * sin)
+    }
+
+    pub fn is_sampled(texture: &Texture) -> bool {
+        matches!(texture, Texture::HdrImage(_))
+    }
+
+    /// Image coordinates seen in `direction`.
+    fn uv(&self, direction: Vector3) -> (f64, f64) {
+        equirect_uv(rotate(direction, -self.rotation))
+    }
+
+    fn direction(&self, u: f64, v: f64) -> Vector3 {
+        rotate(equirect_direction(u, v), self.rotation)
+    }
+
+    /// Sun paired with a sky background, turned and scaled along with it.
+    pub fn sun_light(&self, texture: &Texture) -> Option<SunLight> {
+        let Texture::Sky(sky) = texture else {
+            return None;
+        };
+        let mut sun = sky.sun_light()?;
+        sun.direction = rotate(sun.direction, self.rotation);
+        sun.strength *= self.intensity;
+        Some(sun)
+    }
+}
+
+/// Turn around the vertical axis by `degrees`, the way `u` increases.
+fn rotate(direction: Vector3, degrees: f64) -> Vector3 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    vec3(
+        direction.x * cos + direction.z * sin,
+        direction.y,
+        direction.z * cos - direction.x * sin,
+    )
+}
+
+/// Coordinates of `direction` in an equirectangular image, with `v` going down from the top row.
+pub(super) fn equirect_uv(direction: Vector3) -> (f64, f64) {
+    let d = direction.normalize();
+    let phi = (-d.z).atan2(d.x) + PI;
+    let u = phi.rem_euclid(2.0 * PI) / (2.0 * PI);
+    let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+pub(super) fn equirect_direction(u: f64, v: f64) -> Vector3 {
+    let phi = 2.0 * PI * u - PI;
+    let theta = PI * v;
+    vec3(
+        theta.sin() * phi.cos(),
+        theta.cos(),
+        -theta.sin() * phi.sin(),
+    )
+}
+
+fn distribution(image: &HdrImage) -> &Distribution {
+    image.distribution.get_or_init(|| Distribution::new(image))
+}
+
+/// Piecewise constant density over the pixels of an equirectangular image,
+/// following their luminance and the solid angle they cover.
+pub struct Distribution {
+    width: u32,
+    height: u32,
+    /// Cumulative weights along each row, `width + 1` entries per row.
+    rows: Vec<f64>,
+    /// Cumulative weights of whole rows.
+    marginal: Vec<f64>,
+}
+
+impl Distribution {
+    fn new(image: &HdrImage) -> Self {
+        let (width, height) = image.dimensions();
+        let mut rows = Vec::with_capacity(((width + 1) * height) as usize);
+        let mut marginal = Vec::with_capacity(height as usize + 1);
+        marginal.push(0.0);
+        for j in 0..height {
+            let sin = (PI * (j as f64 + 0.5) / height as f64).sin();
+            let mut sum = 0.0;
+            rows.push(sum);
+            for i in 0..width {
+                let c = image
+                    .colour_space()
+                    .decode(Colour::from(image.get_pixel(i, j).0));
+                let luminance = 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z;
+                sum += luminance.max(0.0) as f64 * sin;
+                rows.push(sum);
+            }
+            marginal.push(marginal[j as usize] + sum);
+        }
+        Self {
+            width,
+            height,
+            rows,
+            marginal,
+        }
+    }
+
+    fn total(&self) -> f64 {
+        self.marginal[self.height as usize]
+    }
+
+    fn row(&self, j: u32) -> &[f64] {
+        let start = (j * (self.width + 1)) as usize;
+        &self.rows[start..start + self.width as usize + 1]
+    }
+
+    /// Point of the image and its density over the unit square.
+    fn sample(&self) -> Option<(f64, f64, f64)> {
+        let total = self.total();
+        if total <= 0.0 {
+            return None;
+        }
+        let j = pick(&self.marginal, random_f64(0.0, total));
+        let row = self.row(j);
+        let i = pick(row, random_f64(0.0, row[self.width as usize]));
+        let u = (i as f64 + random_f64(0.0, 1.0)) / self.width as f64;
+        let v = (j as f64 + random_f64(0.0, 1.0)) / self.height as f64;
+        Some((u, v, self.pdf(u, v)))
+    }
+
+    fn pdf(&self, u: f64, v: f64) -> f64 {
+        let total = self.total();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let (i, j) = pixel_at(u, v, self.width, self.height);
+        let row = self.row(j);
+        let weight = row[i as usize + 1] - row[i as usize];
+        weight * (self.width * self.height) as f64 / total
+    }
+}
+
+/// Index of the interval of the cumulative weights `cdf` holding `value`.
+fn pick(cdf: &[f64], value: f64) -> u32 {
+    let index = cdf.partition_point(|&c| c <= value);
+    index.clamp(1, cdf.len() - 1) as u32 - 1
+}
@@ -0,0 +1,299 @@
+use std::sync::OnceLock;
+
+use image::{GrayImage, Rgb, Rgb32FImage, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    texture::{ColourSpace, TexCoord},
+    Colour,
+};
+
+/// What lies past the edges of an image.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum WrapMode {
+    /// The edge pixels stretched outwards.
+    #[default]
+    Clamp,
+    /// The image tiled.
+    Repeat,
+    /// The image tiled, every other copy flipped so edges meet seamlessly.
+    Mirror,
+}
+
+impl WrapMode {
+    /// Texel index `i` brought inside `0..n`.
+    fn index(self, i: i64, n: u32) -> u32 {
+        let n = n as i64;
+        let i = match self {
+            WrapMode::Clamp => i.clamp(0, n - 1),
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                if i < n {
+                    i
+                } else {
+                    2 * n - 1 - i
+                }
+            }
+        };
+        i as u32
+    }
+}
+
+/// How texels are blended where a lookup falls between them.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Filter {
+    /// The closest texel, sharp and blocky.
+    #[default]
+    Nearest,
+    /// Blend of the four closest texels.
+    Bilinear,
+    /// Catmull-Rom spline through the sixteen closest texels, crisper than bilinear.
+    Bicubic,
+    /// Bilinear on the two mipmap levels matching the ray footprint,
+    /// so that detail smaller than a pixel is averaged instead of shimmering.
+    Trilinear,
+}
+
+/// How an image is looked up between and beyond its pixels.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sampler {
+    pub wrap: WrapMode,
+    pub filter: Filter,
+}
+
+/// Pixels of one level of an image, as linear colours.
+pub(super) trait Texels {
+    fn size(&self) -> (u32, u32);
+    fn texel(&self, i: u32, j: u32) -> Colour;
+}
+
+/// Mipmap levels, already linear.
+impl Texels for Rgb32FImage {
+    fn size(&self) -> (u32, u32) {
+        self.dimensions()
+    }
+
+    fn texel(&self, i: u32, j: u32) -> Colour {
+        Colour::from(self.get_pixel(i, j).0)
+    }
+}
+
+/// Alpha channels, the same in all three channels.
+impl Texels for GrayImage {
+    fn size(&self) -> (u32, u32) {
+        self.dimensions()
+    }
+
+    fn texel(&self, i: u32, j: u32) -> Colour {
+        let alpha = self.get_pixel(i, j).0[0] as f32 / 255.0;
+        Colour::new(alpha, alpha, alpha)
+    }
+}
+
+/// An image read through the colour space its values are stored in.
+pub(super) struct Decoded<'a, I> {
+    pub image: &'a I,
+    pub space: ColourSpace,
+}
+
+impl Texels for Decoded<'_, RgbImage> {
+    fn size(&self) -> (u32, u32) {
+        self.image.dimensions()
+    }
+
+    fn texel(&self, i: u32, j: u32) -> Colour {
+        self.space.decode_u8(self.image.get_pixel(i, j))
+    }
+}
+
+impl Texels for Decoded<'_, Rgb32FImage> {
+    fn size(&self) -> (u32, u32) {
+        self.image.dimensions()
+    }
+
+    fn texel(&self, i: u32, j: u32) -> Colour {
+        self.space
+            .decode(Colour::from(self.image.get_pixel(i, j).0))
+    }
+}
+
+/// Halved copies of an image down to a single pixel, the full size one left out.
+pub struct MipChain {
+    levels: Vec<Rgb32FImage>,
+}
+
+impl MipChain {
+    pub(super) fn new(image: &dyn Texels) -> Self {
+        let mut levels: Vec<Rgb32FImage> = Vec::new();
+        loop {
+            let previous: &dyn Texels = levels.last().map_or(image, |l| l);
+            let (width, height) = previous.size();
+            if width == 1 && height == 1 {
+                break;
+            }
+            let level = Rgb32FImage::from_fn((width / 2).max(1), (height / 2).max(1), |i, j| {
+                let texel = |di: u32, dj: u32| {
+                    previous.texel((2 * i + di).min(width - 1), (2 * j + dj).min(height - 1))
+                };
+                Rgb(((texel(0, 0) + texel(1, 0) + texel(0, 1) + texel(1, 1)) / 4.0).into())
+            });
+            levels.push(level);
+        }
+        Self { levels }
+    }
+}
+
+impl Sampler {
+    /// `mips` is only built when trilinear filtering first needs it.
+    pub(super) fn colour_at(
+        &self,
+        image: &dyn Texels,
+        mips: &OnceLock<MipChain>,
+        at: TexCoord,
+    ) -> Colour {
+        let (u, v) = (at.u, at.v);
+        match self.filter {
+            Filter::Nearest => self.nearest(image, u, v),
+            Filter::Bilinear => self.bilinear(image, u, v),
+            Filter::Bicubic => self.bicubic(image, u, v),
+            Filter::Trilinear => {
+                let (width, height) = image.size();
+                let texels = (at.footprint.0 * width as f64).max(at.footprint.1 * height as f64);
+                if texels <= 1.0 {
+                    return self.bilinear(image, u, v);
+                }
+                let chain = mips.get_or_init(|| MipChain::new(image));
+                let level = texels.log2().min(chain.levels.len() as f64);
+                let below = level.floor() as usize;
+                let level_at = |l: usize| match l {
+                    0 => self.bilinear(image, u, v),
+                    l => self.bilinear(&chain.levels[(l - 1).min(chain.levels.len() - 1)], u, v),
+                };
+                let (a, b) = (level_at(below), level_at(below + 1));
+                a + (b - a) * (level - below as f64) as f32
+            }
+        }
+    }
+
+    fn texel(&self, image: &dyn Texels, i: i64, j: i64) -> Colour {
+        let (width, height) = image.size();
+        image.texel(self.wrap.index(i, width), self.wrap.index(j, height))
+    }
+
+    fn nearest(&self, image: &dyn Texels, u: f64, v: f64) -> Colour {
+        let (width, height) = image.size();
+        let i = (u * width as f64).floor() as i64;
+        let j = (v * height as f64).floor() as i64;
+        self.texel(image, i, j)
+    }
+
+    /// Texel holding `(u, v)` counted from texel centres, and how far past it the point lies.
+    fn corner(image: &dyn Texels, u: f64, v: f64) -> ((i64, i64), (f32, f32)) {
+        let (width, height) = image.size();
+        let x = u * width as f64 - 0.5;
+        let y = v * height as f64 - 0.5;
+        let (i, j) = (x.floor(), y.floor());
+        ((i as i64, j as i64), ((x - i) as f32, (y - j) as f32))
+    }
+
+    fn bilinear(&self, image: &dyn Texels, u: f64, v: f64) -> Colour {
+        let ((i, j), (fx, fy)) = Self::corner(image, u, v);
+        let row = |j| {
+            let a = self.texel(image, i, j);
+            a + (self.texel(image, i + 1, j) - a) * fx
+        };
+        let top = row(j);
+        top + (row(j + 1) - top) * fy
+    }
+
+    fn bicubic(&self, image: &dyn Texels, u: f64, v: f64) -> Colour {
+        let ((i, j), (fx, fy)) = Self::corner(image, u, v);
+        let (wx, wy) = (catmull_rom(fx), catmull_rom(fy));
+        let mut sum = Colour::new(0.0, 0.0, 0.0);
+        for (dj, wy) in wy.iter().enumerate() {
+            for (di, wx) in wx.iter().enumerate() {
+                let texel = self.texel(image, i + di as i64 - 1, j + dj as i64 - 1);
+                sum += texel * (wx * wy);
+            }
+        }
+        // The spline overshoots at sharp edges
+        sum.map(|c| c.max(0.0))
+    }
+}
+
+/// Weights of the four texels around a point `t` past the second one.
+fn catmull_rom(t: f32) -> [f32; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+    [
+        0.5 * (-t3 + 2.0 * t2 - t),
+        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_holds_edge_texels() {
+        let indices: Vec<_> = (-2..6).map(|i| WrapMode::Clamp.index(i, 4)).collect();
+        assert_eq!(indices, [0, 0, 0, 1, 2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn repeat_tiles_both_ways() {
+        let indices: Vec<_> = (-5..6).map(|i| WrapMode::Repeat.index(i, 4)).collect();
+        assert_eq!(indices, [3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn mirror_repeats_edge_texels() {
+        let indices: Vec<_> = (-4..9).map(|i| WrapMode::Mirror.index(i, 4)).collect();
+        assert_eq!(indices, [3, 2, 1, 0, 0, 1, 2, 3, 3, 2, 1, 0, 0]);
+    }
+
+    /// Texels as bright as their column, so each mip level averages to known values.
+    fn columns() -> Rgb32FImage {
+        Rgb32FImage::from_fn(4, 4, |i, _| Rgb([i as f32; 3]))
+    }
+
+    #[test]
+    fn mip_chain_halves_down_to_one_texel() {
+        let chain = MipChain::new(&Rgb32FImage::new(8, 2));
+        let sizes: Vec<_> = chain.levels.iter().map(|l| l.dimensions()).collect();
+        assert_eq!(sizes, [(4, 1), (2, 1), (1, 1)]);
+
+        let chain = MipChain::new(&columns());
+        assert_eq!(chain.levels[0].get_pixel(0, 0).0[0], 0.5);
+        assert_eq!(chain.levels[0].get_pixel(1, 1).0[0], 2.5);
+        assert_eq!(chain.levels[1].get_pixel(0, 0).0[0], 1.5);
+    }
+
+    #[test]
+    fn trilinear_picks_level_by_footprint() {
+        let sampler = Sampler {
+            wrap: WrapMode::Clamp,
+            filter: Filter::Trilinear,
+        };
+        let image = columns();
+        let mips = OnceLock::new();
+        let at = |footprint: f64| {
+            let at = TexCoord {
+                footprint: (footprint, footprint),
+                ..TexCoord::uv(0.125, 0.5)
+            };
+            sampler.colour_at(&image, &mips, at).x
+        };
+        // The first column, then it averaged with the second, then the whole image
+        assert!((at(0.25) - 0.0).abs() < 1e-6);
+        assert!((at(0.5) - 0.5).abs() < 1e-6);
+        assert!((at(1.0) - 1.5).abs() < 1e-6);
+        // Halfway between the last two in log scale
+        assert!((at(2f64.powf(-0.5)) - 1.0).abs() < 1e-6);
+        // Coarser than the image still gives its average
+        assert!((at(16.0) - 1.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,102 @@
+use cgmath::{point3, EuclideanSpace, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::render::{Point3, Ray, Vector3};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Self {
+            min: point3(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: point3(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = Point3>) -> Option<Self> {
+        points
+            .into_iter()
+            .map(|p| Aabb::new(p, p))
+            .reduce(|acc, b| acc.union(&b))
+    }
+
+    pub fn around(center: Point3, half_extent: Vector3) -> Self {
+        Self::new(center - half_extent, center + half_extent)
+    }
+
+    /// Box around a disk of `radius` lying in the plane with given `normal`.
+    pub fn disk(center: Point3, normal: Vector3, radius: f64) -> Self {
+        let n = normal.normalize();
+        let extent = n.map(|x| radius * (1.0 - x * x).max(0.0).sqrt());
+        Self::around(center, extent)
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: point3(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: point3(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn center(&self) -> Point3 {
+        self.min.midpoint(self.max)
+    }
+
+    pub fn corners(&self) -> [Point3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            point3(a.x, a.y, a.z),
+            point3(b.x, a.y, a.z),
+            point3(a.x, b.y, a.z),
+            point3(b.x, b.y, a.z),
+            point3(a.x, a.y, b.z),
+            point3(b.x, a.y, b.z),
+            point3(a.x, b.y, b.z),
+            point3(b.x, b.y, b.z),
+        ]
+    }
+
+    pub fn translated(&self, offset: Vector3) -> Self {
+        Self {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    /// Returns distances at which `ray` enters and leaves the box, clipped to `[min_dist, max_dist]`.
+    pub fn hit_range(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<(f64, f64)> {
+        let mut t_min = min_dist;
+        let mut t_max = max_dist;
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaN (ray parallel to and lying on a slab) keeps the previous bounds
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+
+    pub fn hit(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> bool {
+        self.hit_range(ray, min_dist, max_dist).is_some()
+    }
+}
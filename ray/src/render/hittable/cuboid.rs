@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{material::Material, Point3, Ray, Vector3},
+    vec_repo::Id,
+};
+
+use super::{aabb::Aabb, HasMaterial, HitRecord, HittableTrait};
+
+/// Axis-aligned box. Wrap in `RotateY` to turn it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Cuboid {
+    min_point: Point3,
+    max_point: Point3,
+    material_id: Id<Material>,
+}
+
+impl Cuboid {
+    pub fn new(a: Point3, b: Point3, material_id: Id<Material>) -> Self {
+        let Aabb { min, max } = Aabb::new(a, b);
+        Self {
+            min_point: min,
+            max_point: max,
+            material_id,
+        }
+    }
+
+    pub fn size(&self) -> Vector3 {
+        self.max_point - self.min_point
+    }
+
+    pub fn set_size(&mut self, size: Vector3) {
+        self.max_point = self.min_point + size.map(f64::abs);
+    }
+}
+
+impl HittableTrait for Cuboid {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        let mut t_enter = (f64::NEG_INFINITY, 0);
+        let mut t_exit = (f64::INFINITY, 0);
+        for axis in 0..3 {
+            let inv_d = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min_point[axis] - ray.origin[axis]) * inv_d;
+            let mut t1 = (self.max_point[axis] - ray.origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            if t0 > t_enter.0 {
+                t_enter = (t0, axis);
+            }
+            if t1 < t_exit.0 {
+                t_exit = (t1, axis);
+            }
+        }
+        if t_enter.0 > t_exit.0 {
+            return None;
+        }
+        let (t, axis) = if t_enter.0 >= min_dist {
+            t_enter
+        } else {
+            t_exit
+        };
+        if t < min_dist || t > max_dist {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let size = self.size();
+        let mut outward_normal = Vector3::new(0.0, 0.0, 0.0);
+        outward_normal[axis] = if point[axis] - self.min_point[axis] > size[axis] / 2.0 {
+            1.0
+        } else {
+            -1.0
+        };
+        // Same parametrisation as the faces of `Rect`
+        let (a, b) = match axis {
+            0 => (1, 2),
+            1 => (0, 2),
+            _ => (0, 1),
+        };
+        let u = (point[a] - self.min_point[a]) / size[a];
+        let v = (point[b] - self.min_point[b]) / size[b];
+        let mut dpdu = Vector3::new(0.0, 0.0, 0.0);
+        let mut dpdv = Vector3::new(0.0, 0.0, 0.0);
+        dpdu[a] = size[a];
+        dpdv[b] = size[b];
+        Some(
+            HitRecord::new(ray, t, outward_normal, (u, v), self.material_id)
+                .with_tangents(dpdu, dpdv),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min_point, self.max_point))
+    }
+
+    fn get_position(&self) -> Point3 {
+        self.min_point
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.max_point += c - self.min_point;
+        self.min_point = c;
+    }
+
+    fn name(&self) -> &'static str {
+        "Cuboid"
+    }
+}
+
+impl HasMaterial for Cuboid {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
@@ -0,0 +1,128 @@
+use cgmath::{vec3, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{material::Material, onb::Onb, Point3, Ray, Vector3},
+    vec_repo::Id,
+};
+
+use super::{
+    aabb::Aabb,
+    disk::{polar_dpdu, polar_uv},
+    HasMaterial, HitRecord, HittableTrait, LocalHit,
+};
+
+/// Cylinder standing on the disk at `center`, extending `height` along `axis`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Cylinder {
+    pub center: Point3,
+    pub axis: Vector3,
+    pub radius: f64,
+    pub height: f64,
+    pub capped: bool,
+    material_id: Id<Material>,
+}
+
+impl Cylinder {
+    pub fn new(
+        center: Point3,
+        axis: Vector3,
+        radius: f64,
+        height: f64,
+        material_id: Id<Material>,
+    ) -> Self {
+        Self {
+            center,
+            axis: axis.normalize(),
+            radius,
+            height,
+            capped: true,
+            material_id,
+        }
+    }
+}
+
+impl HittableTrait for Cylinder {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        let onb = Onb::from_w(self.axis);
+        let o = onb.to_local(ray.origin - self.center);
+        let d = onb.to_local(ray.direction);
+
+        let mut closest: Option<LocalHit> = None;
+        let mut consider = |t: f64, normal: Vector3, uv: (f64, f64), tangents: [Vector3; 2]| {
+            if t >= min_dist && t <= max_dist && closest.is_none_or(|(c, ..)| t < c) {
+                closest = Some((t, normal, uv, tangents));
+            }
+        };
+
+        let a = d.x * d.x + d.y * d.y;
+        let half_b = o.x * d.x + o.y * d.y;
+        let c = o.x * o.x + o.y * o.y - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if a > 0.0 && discriminant >= 0.0 {
+            let sqrt_d = discriminant.sqrt();
+            for t in [(-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a] {
+                let p = o + d * t;
+                if p.z >= 0.0 && p.z <= self.height {
+                    let normal = vec3(p.x, p.y, 0.0) / self.radius;
+                    let (u, _) = polar_uv(p.x, p.y);
+                    let tangents = [polar_dpdu(p), vec3(0.0, 0.0, self.height)];
+                    consider(t, normal, (u, p.z / self.height), tangents);
+                }
+            }
+        }
+
+        if self.capped && d.z != 0.0 {
+            for (z, normal_z) in [(0.0, -1.0), (self.height, 1.0)] {
+                let t = (z - o.z) / d.z;
+                let p = o + d * t;
+                if p.x * p.x + p.y * p.y <= self.radius * self.radius {
+                    let uv = (
+                        0.5 + p.x / (2.0 * self.radius),
+                        0.5 + p.y / (2.0 * self.radius),
+                    );
+                    let diameter = 2.0 * self.radius;
+                    let tangents = [vec3(diameter, 0.0, 0.0), vec3(0.0, diameter, 0.0)];
+                    consider(t, vec3(0.0, 0.0, normal_z), uv, tangents);
+                }
+            }
+        }
+
+        closest.map(|(t, normal, uv, [dpdu, dpdv])| {
+            HitRecord::new(ray, t, onb.to_world(normal), uv, self.material_id)
+                .with_tangents(onb.to_world(dpdu), onb.to_world(dpdv))
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let base = Aabb::disk(self.center, self.axis, self.radius);
+        let top = Aabb::disk(
+            self.center + self.axis.normalize() * self.height,
+            self.axis,
+            self.radius,
+        );
+        Some(base.union(&top))
+    }
+
+    fn get_position(&self) -> Point3 {
+        self.center
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.center = c;
+    }
+
+    fn name(&self) -> &'static str {
+        "Cylinder"
+    }
+}
+
+impl HasMaterial for Cylinder {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
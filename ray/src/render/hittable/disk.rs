@@ -0,0 +1,102 @@
+use cgmath::{vec3, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{material::Material, onb::Onb, Point3, Ray, Vector3},
+    vec_repo::Id,
+};
+
+use super::{aabb::Aabb, HasMaterial, HitRecord, HittableTrait};
+
+/// Flat disk (or annulus, if `inner_radius` is positive) facing `normal`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Disk {
+    pub center: Point3,
+    pub normal: Vector3,
+    pub radius: f64,
+    pub inner_radius: f64,
+    material_id: Id<Material>,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vector3, radius: f64, material_id: Id<Material>) -> Self {
+        Self {
+            center,
+            normal: normal.normalize(),
+            radius,
+            inner_radius: 0.0,
+            material_id,
+        }
+    }
+}
+
+/// Angle around the local `z` axis mapped to `[0, 1)`, and distance from the axis.
+pub(super) fn polar_uv(x: f64, y: f64) -> (f64, f64) {
+    let pi = std::f64::consts::PI;
+    let phi = y.atan2(x) + pi;
+    (phi / (2.0 * pi), (x * x + y * y).sqrt())
+}
+
+/// Derivative of `p` along the `u` of `polar_uv`.
+pub(super) fn polar_dpdu(p: Vector3) -> Vector3 {
+    vec3(-p.y, p.x, 0.0) * (2.0 * std::f64::consts::PI)
+}
+
+impl HittableTrait for Disk {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        let onb = Onb::from_w(self.normal);
+        let o = onb.to_local(ray.origin - self.center);
+        let d = onb.to_local(ray.direction);
+        if d.z == 0.0 {
+            return None;
+        }
+        let t = -o.z / d.z;
+        if t < min_dist || t > max_dist {
+            return None;
+        }
+        let p = o + d * t;
+        let (u, dist) = polar_uv(p.x, p.y);
+        if dist > self.radius || dist < self.inner_radius {
+            return None;
+        }
+        let v = (self.radius - dist) / (self.radius - self.inner_radius);
+        let radial = vec3(p.x, p.y, 0.0) / dist.max(1e-12);
+        let dpdv = -radial * (self.radius - self.inner_radius);
+        Some(
+            HitRecord::new(
+                ray,
+                t,
+                onb.to_world(vec3(0.0, 0.0, 1.0)),
+                (u, v),
+                self.material_id,
+            )
+            .with_tangents(onb.to_world(polar_dpdu(p)), onb.to_world(dpdv)),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::disk(self.center, self.normal, self.radius))
+    }
+
+    fn get_position(&self) -> Point3 {
+        self.center
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.center = c;
+    }
+
+    fn name(&self) -> &'static str {
+        "Disk"
+    }
+}
+
+impl HasMaterial for Disk {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
@@ -0,0 +1,336 @@
+use cgmath::{point3, vec3, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{material::Material, rgb_to_vec, texture::Image, Point3, Ray, Vector3},
+    vec_repo::Id,
+};
+
+use super::{aabb::Aabb, HasMaterial, HitRecord, HittableTrait};
+
+/// Terrain made of two triangles per pixel of a grayscale image.
+/// Spans `size.x` by `size.z` from `corner`, white pixels are `size.y` high.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "HeightfieldData")]
+pub struct Heightfield {
+    pub corner: Point3,
+    pub size: Vector3,
+    image: Image,
+    max_level: f64,
+    material_id: Id<Material>,
+}
+
+impl Heightfield {
+    pub fn new(image: Image, corner: Point3, size: Vector3, material_id: Id<Material>) -> Self {
+        assert!(has_cells(&image), "Heightfield needs at least 2x2 pixels");
+        let max_level = image
+            .pixels()
+            .map(|p| luminance(rgb_to_vec(p).cast().unwrap()))
+            .fold(0.0, f64::max);
+        Self {
+            corner,
+            size,
+            image,
+            max_level,
+            material_id,
+        }
+    }
+
+    fn cells(&self) -> (usize, usize) {
+        (
+            self.image.width() as usize - 1,
+            self.image.height() as usize - 1,
+        )
+    }
+
+    fn cell_size(&self) -> (f64, f64) {
+        let (nx, nz) = self.cells();
+        (self.size.x / nx as f64, self.size.z / nz as f64)
+    }
+
+    fn level(&self, i: usize, j: usize) -> f64 {
+        let i = i.min(self.image.width() as usize - 1) as u32;
+        let j = j.min(self.image.height() as usize - 1) as u32;
+        luminance(rgb_to_vec(self.image.get_pixel(i, j)).cast().unwrap())
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        let (dx, dz) = self.cell_size();
+        point3(
+            self.corner.x + i as f64 * dx,
+            self.corner.y + self.level(i, j) * self.size.y,
+            self.corner.z + j as f64 * dz,
+        )
+    }
+
+    fn vertex_normal(&self, i: usize, j: usize) -> Vector3 {
+        let (dx, dz) = self.cell_size();
+        let height = |i, j| self.level(i, j) * self.size.y;
+        // One-sided at the border, so the step must be clamped like the lookup is
+        let (il, ir) = (
+            i.saturating_sub(1),
+            (i + 1).min(self.image.width() as usize - 1),
+        );
+        let (jl, jr) = (
+            j.saturating_sub(1),
+            (j + 1).min(self.image.height() as usize - 1),
+        );
+        let slope_x = (height(ir, j) - height(il, j)) / ((ir - il) as f64 * dx);
+        let slope_z = (height(i, jr) - height(i, jl)) / ((jr - jl) as f64 * dz);
+        vec3(-slope_x, 1.0, -slope_z).normalize()
+    }
+
+    /// Both triangles of a cell, returns distance and smooth normal of the closest hit.
+    fn hit_cell(
+        &self,
+        ray: &Ray,
+        (i, j): (usize, usize),
+        min_dist: f64,
+        max_dist: f64,
+    ) -> Option<(f64, Vector3)> {
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let [v00, v10, v11, v01] = corners.map(|(i, j)| self.vertex(i, j));
+        let triangles = [([0, 1, 2], [v00, v10, v11]), ([0, 2, 3], [v00, v11, v01])];
+
+        let mut closest: Option<(f64, Vector3)> = None;
+        for (indices, [a, b, c]) in triangles {
+            let Some((t, b1, b2)) = hit_triangle(ray, a, b, c) else {
+                continue;
+            };
+            if t < min_dist || t > max_dist || closest.is_some_and(|(d, _)| d < t) {
+                continue;
+            }
+            let [na, nb, nc] = indices.map(|k| {
+                let (i, j) = corners[k];
+                self.vertex_normal(i, j)
+            });
+            let normal = (na * (1.0 - b1 - b2) + nb * b1 + nc * b2).normalize();
+            closest = Some((t, normal));
+        }
+        closest
+    }
+}
+
+fn luminance(c: cgmath::Vector3<f64>) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+/// Möller–Trumbore, returns distance and barycentric coordinates of `b` and `c`.
+fn hit_triangle(ray: &Ray, a: Point3, b: Point3, c: Point3) -> Option<(f64, f64, f64)> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = ray.origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = ray.direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some((edge2.dot(q) * inv_det, u, v))
+}
+
+fn has_cells(image: &Image) -> bool {
+    image.width() >= 2 && image.height() >= 2
+}
+
+/// Checked before use, since the constructor's checks don't run on loading.
+#[derive(Deserialize)]
+struct HeightfieldData {
+    corner: Point3,
+    size: Vector3,
+    image: Image,
+    max_level: f64,
+    material_id: Id<Material>,
+}
+
+impl TryFrom<HeightfieldData> for Heightfield {
+    type Error = &'static str;
+
+    fn try_from(value: HeightfieldData) -> Result<Self, Self::Error> {
+        if !has_cells(&value.image) {
+            return Err("Heightfield needs at least 2x2 pixels");
+        }
+        Ok(Self {
+            corner: value.corner,
+            size: value.size,
+            image: value.image,
+            max_level: value.max_level,
+            material_id: value.material_id,
+        })
+    }
+}
+
+impl HittableTrait for Heightfield {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        // The grid traversal below steps towards growing x and z
+        if self.size.x <= 0.0 || self.size.z <= 0.0 {
+            return None;
+        }
+        let bbox = self.bounding_box()?;
+        let (t_start, t_end) = bbox.hit_range(ray, min_dist, max_dist)?;
+
+        // Amanatides & Woo traversal of the cells in the xz plane
+        let (nx, nz) = self.cells();
+        let (dx, dz) = self.cell_size();
+        let start = ray.at(t_start);
+        let grid_x = (start.x - self.corner.x) / dx;
+        let grid_z = (start.z - self.corner.z) / dz;
+        let mut cell_x = (grid_x.floor().max(0.0) as usize).min(nx - 1);
+        let mut cell_z = (grid_z.floor().max(0.0) as usize).min(nz - 1);
+
+        let axis_setup = |dir: f64, grid: f64, cell: usize, size: f64| {
+            if dir > 0.0 {
+                let next = (cell + 1) as f64 - grid;
+                (1isize, t_start + next * size / dir, size / dir)
+            } else if dir < 0.0 {
+                let next = grid - cell as f64;
+                (-1isize, t_start + next * size / -dir, size / -dir)
+            } else {
+                (0isize, f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let (step_x, mut t_max_x, t_delta_x) = axis_setup(ray.direction.x, grid_x, cell_x, dx);
+        let (step_z, mut t_max_z, t_delta_z) = axis_setup(ray.direction.z, grid_z, cell_z, dz);
+
+        loop {
+            let cell_exit = t_max_x.min(t_max_z).min(t_end);
+            // Triangles never reach into neighbouring cells, so the first hit is the closest
+            if let Some((t, normal)) = self.hit_cell(ray, (cell_x, cell_z), min_dist, max_dist) {
+                let point = ray.at(t);
+                let uv = (
+                    (point.x - self.corner.x) / self.size.x,
+                    (point.z - self.corner.z) / self.size.z,
+                );
+                let dpdu = vec3(self.size.x, 0.0, 0.0);
+                let dpdv = vec3(0.0, 0.0, self.size.z);
+                return Some(
+                    HitRecord::new(ray, t, normal, uv, self.material_id).with_tangents(dpdu, dpdv),
+                );
+            }
+            if cell_exit >= t_end {
+                return None;
+            }
+            if t_max_x < t_max_z {
+                let next = cell_x as isize + step_x;
+                if next < 0 || next >= nx as isize {
+                    return None;
+                }
+                cell_x = next as usize;
+                t_max_x += t_delta_x;
+            } else {
+                let next = cell_z as isize + step_z;
+                if next < 0 || next >= nz as isize {
+                    return None;
+                }
+                cell_z = next as usize;
+                t_max_z += t_delta_z;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.corner,
+            self.corner + vec3(self.size.x, self.max_level * self.size.y, self.size.z),
+        ))
+    }
+
+    fn get_position(&self) -> Point3 {
+        self.corner
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.corner = c;
+    }
+
+    fn name(&self) -> &'static str {
+        "Heightfield"
+    }
+}
+
+impl HasMaterial for Heightfield {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    fn flat(width: u32, height: u32) -> Image {
+        Image::new(RgbImage::from_pixel(width, height, Rgb([128, 128, 128])))
+    }
+
+    #[test]
+    fn too_small_image_fails_to_load() {
+        let data = rmp_serde::to_vec(&(
+            point3(0.0, 0.0, 0.0),
+            vec3(1.0, 1.0, 1.0),
+            flat(1, 4),
+            1.0,
+            Id::<Material>::default(),
+        ))
+        .unwrap();
+        assert!(rmp_serde::from_slice::<Heightfield>(&data).is_err());
+    }
+
+    #[test]
+    fn ray_from_above_hits_flat_terrain() {
+        let field = Heightfield::new(
+            flat(4, 4),
+            point3(0.0, 0.0, 0.0),
+            vec3(2.0, 1.0, 2.0),
+            Id::default(),
+        );
+        let ray = Ray::new(point3(0.7, 5.0, 1.3), vec3(0.0, -1.0, 0.0), 0.0);
+        let hit = field.hit_bounded(&ray, 1e-3, f64::INFINITY).unwrap();
+        let level = field.level(0, 0);
+        assert!((hit.point.y - level).abs() < 1e-9);
+        assert!((hit.normal - vec3(0.0, 1.0, 0.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn negative_size_hits_nothing() {
+        let mut field = Heightfield::new(
+            flat(4, 4),
+            point3(0.0, 0.0, 0.0),
+            vec3(2.0, 1.0, 2.0),
+            Id::default(),
+        );
+        field.size.x = -2.0;
+        let ray = Ray::new(point3(-0.7, 5.0, 1.3), vec3(0.0, -1.0, 0.0), 0.0);
+        assert!(field.hit_bounded(&ray, 1e-3, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn tilted_plane_keeps_its_normal_at_the_border() {
+        let ramp = RgbImage::from_fn(4, 4, |i, _| Rgb([(i * 60) as u8; 3]));
+        let field = Heightfield::new(
+            Image::new(ramp),
+            point3(0.0, 0.0, 0.0),
+            vec3(3.0, 1.0, 3.0),
+            Id::default(),
+        );
+        let inside = field.vertex_normal(1, 1);
+        for (i, j) in [(0, 0), (3, 0), (0, 3), (3, 3), (3, 1)] {
+            assert!((field.vertex_normal(i, j) - inside).magnitude() < 1e-6);
+        }
+        assert!(inside.x < 0.0, "the plane rises towards +x");
+    }
+}
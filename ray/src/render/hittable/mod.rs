@@ -4,23 +4,54 @@ use serde::{Deserialize, Serialize};
 
 use crate::vec_repo::Id;
 
-use super::{material::Material, Point3, Ray, Vector3};
+use super::{material::Material, onb::Onb, Point3, Ray, Vector3};
 
+pub mod aabb;
+pub mod cone;
+pub mod cuboid;
+pub mod cylinder;
+pub mod disk;
+pub mod heightfield;
 pub mod modifiers;
+pub mod moving_sphere;
+pub mod plane;
+pub mod quad;
 pub mod rect;
+pub mod sdf;
 pub mod sphere;
+pub mod torus;
 
+use aabb::Aabb;
+use cone::Cone;
+use cuboid::Cuboid;
+use cylinder::Cylinder;
+use disk::Disk;
+use heightfield::Heightfield;
 use modifiers::*;
+use moving_sphere::MovingSphere;
+use plane::Plane;
+use quad::Quad;
 use rect::Rect;
+use sdf::Sdf;
 use sphere::Sphere;
+use torus::Torus;
 
+/// Distance, normal, uv and tangents of a hit in a shape's own frame.
+type LocalHit = (f64, Vector3, (f64, f64), [Vector3; 2]);
+
+#[derive(Clone, Copy)]
 pub struct HitRecord {
     pub point: Point3,
     pub normal: Vector3,
+    /// Change of `point` along the surface as `uv` grows, for texture space detail.
+    pub dpdu: Vector3,
+    pub dpdv: Vector3,
     pub distance: f64,
     pub uv: (f64, f64),
     pub front_face: bool,
     pub material_id: Id<Material>,
+    /// Width of the ray's cone where it meets the surface, stretched at grazing angles.
+    pub footprint: f64,
 }
 
 impl HitRecord {
@@ -37,32 +68,68 @@ impl HitRecord {
         } else {
             -outward_normal
         };
+        let onb = Onb::from_w(outward_normal);
+        let cos = ray.direction.dot(outward_normal).abs();
         Self {
             point: ray.at(distance),
             normal,
+            dpdu: onb.u,
+            dpdv: onb.v,
             distance,
             uv,
             front_face,
             material_id,
+            footprint: ray.width_at(distance) / cos.max(0.1),
         }
     }
-    fn set_face_normal(&mut self, ray: &Ray, outward_normal: Vector3) {
-        self.front_face = ray.direction.dot(outward_normal) < 0.0;
-        self.normal = if self.front_face {
-            outward_normal
+
+    /// Replaces the arbitrary tangents picked by `new` with ones following `uv`.
+    fn with_tangents(self, dpdu: Vector3, dpdv: Vector3) -> Self {
+        Self { dpdu, dpdv, ..self }
+    }
+
+    /// Normal pointing out of the object, whichever side was hit.
+    pub fn outward_normal(&self) -> Vector3 {
+        if self.front_face {
+            self.normal
         } else {
-            -outward_normal
+            -self.normal
+        }
+    }
+
+    /// Frame around the outward normal with `u` along `dpdu` and `v` on the side of `dpdv`.
+    pub fn tangent_frame(&self) -> Onb {
+        let w = self.outward_normal();
+        let tangent = self.dpdu - w * w.dot(self.dpdu);
+        let u = if tangent.magnitude2() > 1e-18 {
+            tangent.normalize()
+        } else {
+            Onb::from_w(w).u
         };
+        let v = w.cross(u);
+        let v = if v.dot(self.dpdv) < 0.0 { -v } else { v };
+        Onb { u, v, w }
     }
 }
 
-#[enum_dispatch(HittableTrait)]
+#[enum_dispatch(HittableTrait, HasMaterial)]
 #[derive(Clone, Serialize, Deserialize)]
 pub enum Hittable {
     Sphere,
+    MovingSphere,
     Rect,
+    Quad,
+    Cuboid,
+    Cylinder,
+    Cone,
+    Disk,
+    Torus,
+    Plane,
+    Sdf,
+    Heightfield,
     Translate,
     RotateY,
+    Transform,
 }
 
 #[enum_dispatch]
@@ -72,10 +139,19 @@ pub trait HittableTrait {
     fn hit(&self, ray: &Ray) -> Option<HitRecord> {
         self.hit_bounded(ray, f64::EPSILON, f64::INFINITY)
     }
+    /// `None` for unbounded objects, like `Plane`.
+    fn bounding_box(&self) -> Option<Aabb>;
     fn get_position(&self) -> Point3;
     fn set_position(&mut self, c: Point3);
 }
 
+/// Objects drawn with a single material, which groups are not.
+#[enum_dispatch]
+pub trait HasMaterial {
+    fn material_id(&self) -> Id<Material>;
+    fn set_material_id(&mut self, id: Id<Material>);
+}
+
 pub type HittableVec = Vec<Hittable>;
 impl HittableTrait for HittableVec {
     fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
@@ -93,6 +169,13 @@ impl HittableTrait for HittableVec {
         result
     }
 
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.iter()
+            .map(|shape| shape.bounding_box())
+            .reduce(|acc, b| acc.zip(b).map(|(acc, b)| acc.union(&b)))
+            .flatten()
+    }
+
     fn get_position(&self) -> Point3 {
         unimplemented!()
     }
@@ -1,9 +1,12 @@
-use cgmath::EuclideanSpace;
+use cgmath::{point3, vec3, Deg, EuclideanSpace, Euler, InnerSpace, Quaternion};
 use serde::{Deserialize, Serialize};
 
-use crate::render::{Point3, Ray, Vector3};
+use crate::{
+    render::{material::Material, Point3, Ray, Vector3},
+    vec_repo::Id,
+};
 
-use super::{HitRecord, Hittable, HittableTrait};
+use super::{aabb::Aabb, HasMaterial, HitRecord, Hittable, HittableTrait};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Translate {
@@ -19,16 +22,21 @@ impl Translate {
 
 impl HittableTrait for Translate {
     fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
-        let moved_ray = Ray::new(ray.origin - self.offset, ray.direction);
+        let moved_ray = ray.moved(ray.origin - self.offset, ray.direction);
         self.object
             .hit_bounded(&moved_ray, min_dist, max_dist)
-            .and_then(|mut hit| {
+            .map(|mut hit| {
                 hit.point += self.offset;
-                hit.set_face_normal(ray, hit.normal);
-                Some(hit)
+                hit
             })
     }
 
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object
+            .bounding_box()
+            .map(|bbox| bbox.translated(self.offset))
+    }
+
     fn set_position(&mut self, c: Point3) {
         self.offset = c.to_vec();
     }
@@ -42,6 +50,16 @@ impl HittableTrait for Translate {
     }
 }
 
+impl HasMaterial for Translate {
+    fn material_id(&self) -> Id<Material> {
+        self.object.material_id()
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.object.set_material_id(id);
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct RotateY {
     sin_y: f64,
@@ -65,6 +83,7 @@ impl HittableTrait for RotateY {
         let Ray {
             mut origin,
             mut direction,
+            ..
         } = ray;
 
         origin[0] = self.cos_y * ray.origin[0] - self.sin_y * ray.origin[2];
@@ -73,7 +92,7 @@ impl HittableTrait for RotateY {
         direction[0] = self.cos_y * ray.direction[0] - self.sin_y * ray.direction[2];
         direction[2] = self.sin_y * ray.direction[0] + self.cos_y * ray.direction[2];
 
-        let rotated_ray = Ray::new(origin, direction);
+        let rotated_ray = ray.moved(origin, direction);
         let hit = self.object.hit_bounded(&rotated_ray, min_dist, max_dist);
         if hit.is_none() {
             return None;
@@ -81,18 +100,35 @@ impl HittableTrait for RotateY {
         let mut hit = hit.unwrap();
 
         let mut point = hit.point;
-        let mut normal = hit.normal;
         point[0] = self.cos_y * hit.point[0] + self.sin_y * hit.point[2];
         point[2] = -self.sin_y * hit.point[0] + self.cos_y * hit.point[2];
-
-        normal[0] = self.cos_y * hit.normal[0] + self.sin_y * hit.normal[2];
-        normal[2] = -self.sin_y * hit.normal[0] + self.cos_y * hit.normal[2];
+        let rotate = |v: Vector3| {
+            vec3(
+                self.cos_y * v.x + self.sin_y * v.z,
+                v.y,
+                -self.sin_y * v.x + self.cos_y * v.z,
+            )
+        };
 
         hit.point = point;
-        hit.set_face_normal(&rotated_ray, normal);
+        // Rotating keeps the normal facing the ray, so front_face stays as it was
+        hit.normal = rotate(hit.normal);
+        hit.dpdu = rotate(hit.dpdu);
+        hit.dpdv = rotate(hit.dpdv);
         Some(hit)
     }
 
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox = self.object.bounding_box()?;
+        Aabb::from_points(bbox.corners().map(|p| {
+            point3(
+                self.cos_y * p.x + self.sin_y * p.z,
+                p.y,
+                -self.sin_y * p.x + self.cos_y * p.z,
+            )
+        }))
+    }
+
     fn get_position(&self) -> Point3 {
         unimplemented!()
     }
@@ -105,3 +141,206 @@ impl HittableTrait for RotateY {
         "RotateY"
     }
 }
+
+impl HasMaterial for RotateY {
+    fn material_id(&self) -> Id<Material> {
+        self.object.material_id()
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.object.set_material_id(id);
+    }
+}
+
+/// Position, orientation and uniform scale of an object at a moment in time.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub translation: Vector3,
+    /// Euler angles in degrees.
+    pub rotation: Vector3,
+    pub scale: f64,
+}
+
+impl Default for Keyframe {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Vector3::new(0.0, 0.0, 0.0),
+            scale: 1.0,
+        }
+    }
+}
+
+impl Keyframe {
+    fn quaternion(&self) -> Quaternion<f64> {
+        Quaternion::from(Euler::new(
+            Deg(self.rotation.x),
+            Deg(self.rotation.y),
+            Deg(self.rotation.z),
+        ))
+    }
+}
+
+/// Moves, rotates and scales the object, interpolating between `start` at `time0`
+/// and `end` at `time1` to produce motion blur.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Transform {
+    pub start: Keyframe,
+    pub end: Keyframe,
+    pub time0: f64,
+    pub time1: f64,
+    object: Box<Hittable>,
+}
+
+impl Transform {
+    pub fn new(object: Box<Hittable>, keyframe: Keyframe) -> Self {
+        Self::animated(object, (keyframe, 0.0), (keyframe, 1.0))
+    }
+
+    pub fn animated(
+        object: Box<Hittable>,
+        (start, time0): (Keyframe, f64),
+        (end, time1): (Keyframe, f64),
+    ) -> Self {
+        Self {
+            start,
+            end,
+            time0,
+            time1,
+            object,
+        }
+    }
+
+    fn at(&self, time: f64) -> (Vector3, Quaternion<f64>, f64) {
+        let k = if self.time1 == self.time0 {
+            0.0
+        } else {
+            ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0)
+        };
+        let translation =
+            self.start.translation + (self.end.translation - self.start.translation) * k;
+        let rotation = self.start.quaternion().slerp(self.end.quaternion(), k);
+        let scale = self.start.scale + (self.end.scale - self.start.scale) * k;
+        (translation, rotation, scale)
+    }
+}
+
+impl HittableTrait for Transform {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        let (translation, rotation, scale) = self.at(ray.time);
+        let inverse = rotation.conjugate();
+        let origin = inverse * (ray.origin.to_vec() - translation) / scale;
+        let mut local_ray = ray.moved(Point3::from_vec(origin), inverse * ray.direction);
+        local_ray.width /= scale;
+        // Direction stays normalised, so distances shrink with the scale
+        let mut hit = self
+            .object
+            .hit_bounded(&local_ray, min_dist / scale, max_dist / scale)?;
+        hit.point = Point3::from_vec(rotation * hit.point.to_vec() * scale + translation);
+        hit.normal = rotation * hit.normal;
+        hit.dpdu = rotation * hit.dpdu * scale;
+        hit.dpdv = rotation * hit.dpdv * scale;
+        hit.distance *= scale;
+        hit.footprint *= scale;
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox = self.object.bounding_box()?;
+        let keyframes = [self.start, self.end];
+        if self.start.rotation != self.end.rotation {
+            // Slerping swings the object through poses neither keyframe has, but never
+            // further from the origin of the object than its farthest corner
+            let radius = bbox
+                .corners()
+                .iter()
+                .map(|p| p.to_vec().magnitude())
+                .fold(0.0, f64::max);
+            let spheres = keyframes.into_iter().map(|keyframe| {
+                let extent = vec3(1.0, 1.0, 1.0) * radius * keyframe.scale.abs();
+                Aabb::around(Point3::from_vec(keyframe.translation), extent)
+            });
+            return spheres.reduce(|a, b| a.union(&b));
+        }
+        // With a fixed rotation every point moves in a straight line
+        let corners = keyframes.into_iter().flat_map(|keyframe| {
+            let rotation = keyframe.quaternion();
+            bbox.corners().map(|p| {
+                Point3::from_vec(rotation * p.to_vec() * keyframe.scale + keyframe.translation)
+            })
+        });
+        Aabb::from_points(corners)
+    }
+
+    fn get_position(&self) -> Point3 {
+        Point3::from_vec(self.start.translation)
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.end.translation += c.to_vec() - self.start.translation;
+        self.start.translation = c.to_vec();
+    }
+
+    fn name(&self) -> &'static str {
+        "Transform"
+    }
+}
+
+impl HasMaterial for Transform {
+    fn material_id(&self) -> Id<Material> {
+        self.object.material_id()
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.object.set_material_id(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::hittable::sphere::Sphere;
+
+    use super::*;
+
+    fn inside(bbox: &Aabb, p: Point3) -> bool {
+        (0..3).all(|i| bbox.min[i] - 1e-9 <= p[i] && p[i] <= bbox.max[i] + 1e-9)
+    }
+
+    #[test]
+    fn bounding_box_covers_poses_between_keyframes() {
+        let sphere = Sphere::new(point3(5.0, 0.0, 0.0), 1.0, Id::default());
+        let turned = Keyframe {
+            rotation: vec3(0.0, 180.0, 0.0),
+            ..Keyframe::default()
+        };
+        let transform = Transform::animated(
+            Box::new(sphere.into()),
+            (Keyframe::default(), 0.0),
+            (turned, 1.0),
+        );
+        let bbox = transform.bounding_box().unwrap();
+        // Halfway the sphere has swung a quarter turn off the x axis
+        let mid = transform.at(0.5).1 * vec3(5.0, 0.0, 0.0);
+        assert!(mid.x.abs() < 1e-9 && (mid.z.abs() - 5.0).abs() < 1e-9);
+        let ray = Ray::new(point3(0.0, 0.0, 0.0), mid, 0.5);
+        let hit = transform.hit_bounded(&ray, 1e-3, f64::INFINITY).unwrap();
+        assert!(inside(&bbox, hit.point));
+    }
+
+    #[test]
+    fn fixed_rotation_keeps_tight_box() {
+        let sphere = Sphere::new(point3(0.0, 0.0, 0.0), 1.0, Id::default());
+        let moved = Keyframe {
+            translation: vec3(4.0, 0.0, 0.0),
+            ..Keyframe::default()
+        };
+        let transform = Transform::animated(
+            Box::new(sphere.into()),
+            (Keyframe::default(), 0.0),
+            (moved, 1.0),
+        );
+        let bbox = transform.bounding_box().unwrap();
+        assert!((bbox.min - point3(-1.0, -1.0, -1.0)).magnitude() < 1e-9);
+        assert!((bbox.max - point3(5.0, 1.0, 1.0)).magnitude() < 1e-9);
+    }
+}
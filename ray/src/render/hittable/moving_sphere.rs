@@ -0,0 +1,107 @@
+use cgmath::{vec3, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{material::Material, Point3, Ray},
+    vec_repo::Id,
+};
+
+use super::{aabb::Aabb, sphere::Sphere, HasMaterial, HitRecord, HittableTrait};
+
+/// Sphere moving linearly from `center0` at `time0` to `center1` at `time1`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MovingSphere {
+    pub center0: Point3,
+    pub center1: Point3,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    material_id: Id<Material>,
+}
+
+impl MovingSphere {
+    pub fn new(
+        (center0, time0): (Point3, f64),
+        (center1, time1): (Point3, f64),
+        radius: f64,
+        material_id: Id<Material>,
+    ) -> Self {
+        Self {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material_id,
+        }
+    }
+
+    pub fn center(&self, time: f64) -> Point3 {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
+        let k = (time - self.time0) / (self.time1 - self.time0);
+        self.center0 + (self.center1 - self.center0) * k
+    }
+}
+
+impl HittableTrait for MovingSphere {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        let center = self.center(ray.time);
+        let oc = ray.origin - center;
+        let half_b = oc.dot(ray.direction);
+        let c = oc.magnitude2() - self.radius * self.radius;
+        let discriminant = half_b * half_b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let sqrt_d = discriminant.sqrt();
+        let mut root = -half_b - sqrt_d;
+        if root < min_dist || root > max_dist {
+            root = -half_b + sqrt_d;
+            if root < min_dist || root > max_dist {
+                return None;
+            }
+        }
+        let outward_normal = (ray.at(root) - center) / self.radius;
+        let (dpdu, dpdv) = Sphere::get_tangents(&outward_normal, self.radius);
+        Some(
+            HitRecord::new(
+                ray,
+                root,
+                outward_normal,
+                Sphere::get_uv(&outward_normal),
+                self.material_id,
+            )
+            .with_tangents(dpdu, dpdv),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = vec3(self.radius, self.radius, self.radius);
+        Some(Aabb::around(self.center0, extent).union(&Aabb::around(self.center1, extent)))
+    }
+
+    fn get_position(&self) -> Point3 {
+        self.center0
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.center1 += c - self.center0;
+        self.center0 = c;
+    }
+
+    fn name(&self) -> &'static str {
+        "Moving Sphere"
+    }
+}
+
+impl HasMaterial for MovingSphere {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
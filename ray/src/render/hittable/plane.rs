@@ -0,0 +1,76 @@
+use cgmath::InnerSpace;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{material::Material, onb::Onb, Point3, Ray, Vector3},
+    vec_repo::Id,
+};
+
+use super::{aabb::Aabb, HasMaterial, HitRecord, HittableTrait};
+
+/// Infinite plane through `point`. Texture repeats every `uv_scale` units.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Plane {
+    pub point: Point3,
+    pub normal: Vector3,
+    pub uv_scale: f64,
+    material_id: Id<Material>,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vector3, material_id: Id<Material>) -> Self {
+        Self {
+            point,
+            normal: normal.normalize(),
+            uv_scale: 1.0,
+            material_id,
+        }
+    }
+}
+
+impl HittableTrait for Plane {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        let normal = self.normal.normalize();
+        let denom = normal.dot(ray.direction);
+        if denom == 0.0 {
+            return None;
+        }
+        let t = normal.dot(self.point - ray.origin) / denom;
+        if t < min_dist || t > max_dist {
+            return None;
+        }
+        let onb = Onb::from_w(normal);
+        let local = onb.to_local(ray.at(t) - self.point) / self.uv_scale;
+        let uv = (local.x.rem_euclid(1.0), local.y.rem_euclid(1.0));
+        Some(
+            HitRecord::new(ray, t, normal, uv, self.material_id)
+                .with_tangents(onb.u * self.uv_scale, onb.v * self.uv_scale),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    fn get_position(&self) -> Point3 {
+        self.point
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.point = c;
+    }
+
+    fn name(&self) -> &'static str {
+        "Plane"
+    }
+}
+
+impl HasMaterial for Plane {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
@@ -0,0 +1,88 @@
+use cgmath::InnerSpace;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{material::Material, Point3, Ray, Vector3},
+    vec_repo::Id,
+};
+
+use super::{aabb::Aabb, HasMaterial, HitRecord, HittableTrait};
+
+/// Parallelogram spanned by edges `u` and `v` starting at `corner`.
+/// Front face is on the side of `u x v`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Quad {
+    pub corner: Point3,
+    pub u: Vector3,
+    pub v: Vector3,
+    material_id: Id<Material>,
+}
+
+impl Quad {
+    pub fn new(corner: Point3, u: Vector3, v: Vector3, material_id: Id<Material>) -> Self {
+        Self {
+            corner,
+            u,
+            v,
+            material_id,
+        }
+    }
+}
+
+impl HittableTrait for Quad {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        let n = self.u.cross(self.v);
+        let normal = n.normalize();
+        let denom = normal.dot(ray.direction);
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        let t = normal.dot(self.corner - ray.origin) / denom;
+        if t < min_dist || t > max_dist {
+            return None;
+        }
+        // Coordinates of the hit point in the (u, v) basis
+        let w = n / n.magnitude2();
+        let planar = ray.at(t) - self.corner;
+        let alpha = w.dot(planar.cross(self.v));
+        let beta = w.dot(self.u.cross(planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        Some(
+            HitRecord::new(ray, t, normal, (alpha, beta), self.material_id)
+                .with_tangents(self.u, self.v),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points([
+            self.corner,
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ])
+    }
+
+    fn get_position(&self) -> Point3 {
+        self.corner
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.corner = c;
+    }
+
+    fn name(&self) -> &'static str {
+        "Quad"
+    }
+}
+
+impl HasMaterial for Quad {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
@@ -1,16 +1,16 @@
-use std::mem::take;
-
 use cgmath::vec3;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 
 use crate::{
     render::{material::Material, Point3, Ray},
     vec_repo::Id,
 };
 
-use super::{HitRecord, HittableTrait};
+use super::{aabb::Aabb, HasMaterial, HitRecord, HittableTrait};
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Axis-aligned rectangle, `min_point` and `max_point` must share one coordinate.
+/// Use `Quad` for other orientations and `Cuboid` for boxes.
+#[derive(Clone, Serialize)]
 pub struct Rect {
     min_point: Point3,
     max_point: Point3,
@@ -18,12 +18,34 @@ pub struct Rect {
 }
 
 impl Rect {
-    pub fn new(min_point: Point3, max_point: Point3, material_id: Id<Material>) -> Self {
-        Self {
+    /// `None` if the corners share no coordinate, as that would be a box.
+    pub fn new(min_point: Point3, max_point: Point3, material_id: Id<Material>) -> Option<Self> {
+        let flat =
+            min_point.x == max_point.x || min_point.y == max_point.y || min_point.z == max_point.z;
+        flat.then_some(Self {
             min_point,
             max_point,
             material_id,
+        })
+    }
+}
+
+/// Checked like `Rect::new`, so a box saved as a `Rect` fails to load instead of vanishing.
+impl<'de> Deserialize<'de> for Rect {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Corners {
+            min_point: Point3,
+            max_point: Point3,
+            material_id: Id<Material>,
         }
+
+        let c = Corners::deserialize(deserializer)?;
+        Rect::new(c.min_point, c.max_point, c.material_id)
+            .ok_or_else(|| de::Error::custom("Rect corners share no coordinate, use a Cuboid"))
     }
 }
 
@@ -37,6 +59,10 @@ impl HittableTrait for Rect {
         self.min_point = c;
     }
 
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min_point, self.max_point))
+    }
+
     fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
         let (x0, y0, z0) = self.min_point.into();
         let (x1, y1, z1) = self.max_point.into();
@@ -67,62 +93,7 @@ impl HittableTrait for Rect {
                 max_dist,
             );
         }
-        let side1 = hit_plane_xy(
-            ray,
-            [x0, x1, y0, y1, z0],
-            self.material_id,
-            min_dist,
-            max_dist,
-        );
-        let side2 = hit_plane_xy(
-            ray,
-            [x0, x1, y0, y1, z1],
-            self.material_id,
-            min_dist,
-            max_dist,
-        );
-        let side3 = hit_plane_xz(
-            ray,
-            [x0, x1, z0, z1, y0],
-            self.material_id,
-            min_dist,
-            max_dist,
-        );
-        let side4 = hit_plane_xz(
-            ray,
-            [x0, x1, z0, z1, y1],
-            self.material_id,
-            min_dist,
-            max_dist,
-        );
-        let side5 = hit_plane_yz(
-            ray,
-            [y0, y1, z0, z1, x0],
-            self.material_id,
-            min_dist,
-            max_dist,
-        );
-        let side6 = hit_plane_yz(
-            ray,
-            [y0, y1, z0, z1, x1],
-            self.material_id,
-            min_dist,
-            max_dist,
-        );
-        let mut sides = [side1, side2, side3, side4, side5, side6];
-        sides.sort_by(|lhs, rhs| {
-            if rhs.is_none() {
-                return std::cmp::Ordering::Less;
-            }
-            if lhs.is_none() {
-                return std::cmp::Ordering::Greater;
-            }
-            lhs.as_ref()
-                .unwrap()
-                .distance
-                .total_cmp(&rhs.as_ref().unwrap().distance)
-        });
-        take(&mut sides[0])
+        None
     }
 
     fn name(&self) -> &'static str {
@@ -130,6 +101,16 @@ impl HittableTrait for Rect {
     }
 }
 
+impl HasMaterial for Rect {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
+
 fn hit_plane_xy(
     ray: &Ray,
     plane: [f64; 5],
@@ -148,7 +129,10 @@ fn hit_plane_xy(
     }
     let u = (point.x - x0) / (x1 - x0);
     let v = (point.y - y0) / (y1 - y0);
-    Some(HitRecord::new(ray, dist, vec3(0.0, 0.0, 1.0), (u, v), mat))
+    Some(
+        HitRecord::new(ray, dist, vec3(0.0, 0.0, 1.0), (u, v), mat)
+            .with_tangents(vec3(x1 - x0, 0.0, 0.0), vec3(0.0, y1 - y0, 0.0)),
+    )
 }
 
 fn hit_plane_xz(
@@ -169,7 +153,10 @@ fn hit_plane_xz(
     }
     let u = (point.x - x0) / (x1 - x0);
     let v = (point.z - z0) / (z1 - z0);
-    Some(HitRecord::new(ray, dist, vec3(0.0, 1.0, 0.0), (u, v), mat))
+    Some(
+        HitRecord::new(ray, dist, vec3(0.0, 1.0, 0.0), (u, v), mat)
+            .with_tangents(vec3(x1 - x0, 0.0, 0.0), vec3(0.0, 0.0, z1 - z0)),
+    )
 }
 
 fn hit_plane_yz(
@@ -190,5 +177,41 @@ fn hit_plane_yz(
     }
     let u = (point.y - y0) / (y1 - y0);
     let v = (point.z - z0) / (z1 - z0);
-    Some(HitRecord::new(ray, dist, vec3(1.0, 0.0, 0.0), (u, v), mat))
+    Some(
+        HitRecord::new(ray, dist, vec3(1.0, 0.0, 0.0), (u, v), mat)
+            .with_tangents(vec3(0.0, y1 - y0, 0.0), vec3(0.0, 0.0, z1 - z0)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::point3;
+
+    use super::*;
+
+    #[test]
+    fn boxes_are_rejected() {
+        let (a, b) = (point3(0.0, 0.0, 0.0), point3(1.0, 1.0, 1.0));
+        assert!(Rect::new(a, b, Id::default()).is_none());
+        assert!(Rect::new(a, point3(1.0, 0.0, 1.0), Id::default()).is_some());
+    }
+
+    #[test]
+    fn saved_box_fails_to_load() {
+        let data = rmp_serde::to_vec(&(
+            point3(0.0, 0.0, 0.0),
+            point3(1.0, 1.0, 1.0),
+            Id::<Material>::default(),
+        ))
+        .unwrap();
+        assert!(rmp_serde::from_slice::<Rect>(&data).is_err());
+    }
+
+    #[test]
+    fn saved_rect_round_trips() {
+        let rect = Rect::new(point3(0.0, 0.0, 0.0), point3(1.0, 0.0, 1.0), Id::default()).unwrap();
+        let data = rmp_serde::to_vec(&rect).unwrap();
+        let rect: Rect = rmp_serde::from_slice(&data).unwrap();
+        assert_eq!(rect.max_point, point3(1.0, 0.0, 1.0));
+    }
 }
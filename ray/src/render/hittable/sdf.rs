@@ -0,0 +1,429 @@
+use cgmath::{point3, vec3, EuclideanSpace, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{material::Material, Point3, Ray, Vector3},
+    vec_repo::Id,
+};
+
+use super::{aabb::Aabb, sphere::Sphere, HasMaterial, HitRecord, HittableTrait};
+
+const EPSILON: f64 = 1e-4;
+const MAX_DISTANCE: f64 = 1e4;
+
+/// Expression tree of signed distance functions, evaluated in local coordinates.
+/// Primitives are centered at origin, rotational ones are symmetric around `y`.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum SdfNode {
+    Sphere {
+        radius: f64,
+    },
+    Box {
+        half_extent: Vector3,
+    },
+    RoundBox {
+        half_extent: Vector3,
+        radius: f64,
+    },
+    Torus {
+        major_radius: f64,
+        minor_radius: f64,
+    },
+    Cylinder {
+        radius: f64,
+        half_height: f64,
+    },
+    Mandelbulb {
+        power: f64,
+        iterations: u32,
+    },
+    Union(Box<SdfNode>, Box<SdfNode>),
+    Intersection(Box<SdfNode>, Box<SdfNode>),
+    /// First node with the second carved out of it.
+    Subtraction(Box<SdfNode>, Box<SdfNode>),
+    SmoothUnion {
+        a: Box<SdfNode>,
+        b: Box<SdfNode>,
+        smoothness: f64,
+    },
+    Translate {
+        offset: Vector3,
+        node: Box<SdfNode>,
+    },
+    /// A negative `factor` also mirrors the node through the origin.
+    Scale {
+        factor: f64,
+        node: Box<SdfNode>,
+    },
+    /// Rotates around `y` by `rate` radians per unit of height.
+    Twist {
+        rate: f64,
+        node: Box<SdfNode>,
+    },
+    /// Infinite repetition, zero period leaves the axis untouched.
+    Repeat {
+        period: Vector3,
+        node: Box<SdfNode>,
+    },
+}
+
+impl SdfNode {
+    pub fn union(self, other: SdfNode) -> Self {
+        Self::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: SdfNode) -> Self {
+        Self::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn subtraction(self, other: SdfNode) -> Self {
+        Self::Subtraction(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_union(self, other: SdfNode, smoothness: f64) -> Self {
+        Self::SmoothUnion {
+            a: Box::new(self),
+            b: Box::new(other),
+            smoothness,
+        }
+    }
+
+    pub fn translate(self, offset: Vector3) -> Self {
+        Self::Translate {
+            offset,
+            node: Box::new(self),
+        }
+    }
+
+    pub fn scale(self, factor: f64) -> Self {
+        Self::Scale {
+            factor,
+            node: Box::new(self),
+        }
+    }
+
+    pub fn twist(self, rate: f64) -> Self {
+        Self::Twist {
+            rate,
+            node: Box::new(self),
+        }
+    }
+
+    pub fn repeat(self, period: Vector3) -> Self {
+        Self::Repeat {
+            period,
+            node: Box::new(self),
+        }
+    }
+
+    pub fn distance(&self, p: Vector3) -> f64 {
+        match self {
+            Self::Sphere { radius } => p.magnitude() - radius,
+            Self::Box { half_extent } => box_distance(p, *half_extent),
+            Self::RoundBox {
+                half_extent,
+                radius,
+            } => box_distance(p, half_extent.map(|x| x - radius)) - radius,
+            Self::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let q = (p.x * p.x + p.z * p.z).sqrt() - major_radius;
+                (q * q + p.y * p.y).sqrt() - minor_radius
+            }
+            Self::Cylinder {
+                radius,
+                half_height,
+            } => {
+                let dx = (p.x * p.x + p.z * p.z).sqrt() - radius;
+                let dy = p.y.abs() - half_height;
+                dx.max(dy).min(0.0) + (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt()
+            }
+            Self::Mandelbulb { power, iterations } => mandelbulb_distance(p, *power, *iterations),
+            Self::Union(a, b) => a.distance(p).min(b.distance(p)),
+            Self::Intersection(a, b) => a.distance(p).max(b.distance(p)),
+            Self::Subtraction(a, b) => a.distance(p).max(-b.distance(p)),
+            Self::SmoothUnion { a, b, smoothness } => {
+                let (da, db) = (a.distance(p), b.distance(p));
+                if *smoothness <= 0.0 {
+                    return da.min(db);
+                }
+                let h = (0.5 + 0.5 * (db - da) / smoothness).clamp(0.0, 1.0);
+                db + (da - db) * h - smoothness * h * (1.0 - h)
+            }
+            Self::Translate { offset, node } => node.distance(p - offset),
+            Self::Scale { factor, node } => node.distance(p / *factor) * factor.abs(),
+            Self::Twist { rate, node } => {
+                let (s, c) = (rate * p.y).sin_cos();
+                node.distance(vec3(c * p.x - s * p.z, p.y, s * p.x + c * p.z))
+            }
+            Self::Repeat { period, node } => {
+                let mut q = p;
+                for axis in 0..3 {
+                    if period[axis] > 0.0 {
+                        q[axis] -= period[axis] * (p[axis] / period[axis]).round();
+                    }
+                }
+                node.distance(q)
+            }
+        }
+    }
+
+    /// `None` when the shape is infinite.
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        let origin = point3(0.0, 0.0, 0.0);
+        match self {
+            Self::Sphere { radius } => Some(Aabb::around(origin, vec3(1.0, 1.0, 1.0) * *radius)),
+            Self::Box { half_extent } | Self::RoundBox { half_extent, .. } => {
+                Some(Aabb::around(origin, *half_extent))
+            }
+            Self::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let r = major_radius + minor_radius;
+                Some(Aabb::around(origin, vec3(r, *minor_radius, r)))
+            }
+            Self::Cylinder {
+                radius,
+                half_height,
+            } => Some(Aabb::around(origin, vec3(*radius, *half_height, *radius))),
+            Self::Mandelbulb { .. } => Some(Aabb::around(origin, vec3(1.2, 1.2, 1.2))),
+            Self::Union(a, b) => Some(a.bounding_box()?.union(&b.bounding_box()?)),
+            Self::Intersection(a, b) => match (a.bounding_box(), b.bounding_box()) {
+                (Some(a), Some(b)) => Some(Aabb::new(
+                    point3(
+                        a.min.x.max(b.min.x),
+                        a.min.y.max(b.min.y),
+                        a.min.z.max(b.min.z),
+                    ),
+                    point3(
+                        a.max.x.min(b.max.x),
+                        a.max.y.min(b.max.y),
+                        a.max.z.min(b.max.z),
+                    ),
+                )),
+                (a, b) => a.or(b),
+            },
+            Self::Subtraction(a, _) => a.bounding_box(),
+            Self::SmoothUnion { a, b, smoothness } => {
+                let bbox = a.bounding_box()?.union(&b.bounding_box()?);
+                let pad = vec3(1.0, 1.0, 1.0) * smoothness.max(0.0);
+                Some(Aabb::new(bbox.min - pad, bbox.max + pad))
+            }
+            Self::Translate { offset, node } => {
+                node.bounding_box().map(|bbox| bbox.translated(*offset))
+            }
+            // `Aabb::new` sorts the corners again when `factor` mirrors them
+            Self::Scale { factor, node } => node
+                .bounding_box()
+                .map(|bbox| Aabb::new(bbox.min * *factor, bbox.max * *factor)),
+            Self::Twist { node, .. } => {
+                let bbox = node.bounding_box()?;
+                let r = radius_around_y(&bbox);
+                Some(Aabb::new(
+                    point3(-r, bbox.min.y, -r),
+                    point3(r, bbox.max.y, r),
+                ))
+            }
+            Self::Repeat { .. } => None,
+        }
+    }
+
+    /// Upper bound on how fast the distance changes, > 1 when a node distorts space.
+    fn lipschitz(&self) -> f64 {
+        match self {
+            Self::Union(a, b) | Self::Intersection(a, b) | Self::Subtraction(a, b) => {
+                a.lipschitz().max(b.lipschitz())
+            }
+            Self::SmoothUnion { a, b, .. } => a.lipschitz().max(b.lipschitz()),
+            Self::Translate { node, .. } | Self::Scale { node, .. } => node.lipschitz(),
+            Self::Repeat { node, .. } => node.lipschitz(),
+            Self::Twist { rate, node } => {
+                let r = node
+                    .bounding_box()
+                    .map(|bbox| radius_around_y(&bbox))
+                    .unwrap_or(1.0);
+                node.lipschitz() * (1.0 + (rate * r).powi(2)).sqrt()
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+fn box_distance(p: Vector3, half_extent: Vector3) -> f64 {
+    let q = p.map(f64::abs) - half_extent;
+    q.map(|x| x.max(0.0)).magnitude() + q.x.max(q.y).max(q.z).min(0.0)
+}
+
+fn radius_around_y(bbox: &Aabb) -> f64 {
+    bbox.corners()
+        .iter()
+        .map(|c| (c.x * c.x + c.z * c.z).sqrt())
+        .fold(0.0, f64::max)
+}
+
+fn mandelbulb_distance(p: Vector3, power: f64, iterations: u32) -> f64 {
+    let mut z = p;
+    let mut dr = 1.0;
+    let mut r = z.magnitude();
+    for _ in 0..iterations {
+        r = z.magnitude();
+        if r > 2.0 {
+            break;
+        }
+        if r < f64::EPSILON {
+            // No angles at the origin, where z to the power is zero anyway
+            z = p;
+            dr = 1.0;
+            continue;
+        }
+        let theta = (z.z / r).clamp(-1.0, 1.0).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        dr = r.powf(power - 1.0) * power * dr + 1.0;
+        let zr = r.powf(power);
+        z = vec3(
+            theta.sin() * phi.cos(),
+            phi.sin() * theta.sin(),
+            theta.cos(),
+        ) * zr
+            + p;
+    }
+    if r < f64::EPSILON {
+        return 0.0;
+    }
+    0.5 * r.ln() * r / dr
+}
+
+/// Shape described by a signed distance function, intersected by sphere tracing.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sdf {
+    pub position: Point3,
+    pub root: SdfNode,
+    pub max_steps: u32,
+    material_id: Id<Material>,
+}
+
+impl Sdf {
+    pub fn new(position: Point3, root: SdfNode, material_id: Id<Material>) -> Self {
+        Self {
+            position,
+            root,
+            max_steps: 256,
+            material_id,
+        }
+    }
+
+    fn normal_at(&self, p: Vector3) -> Vector3 {
+        // Tetrahedron technique, four samples instead of six
+        let h = EPSILON * 0.5;
+        let k = [
+            vec3(1.0, -1.0, -1.0),
+            vec3(-1.0, -1.0, 1.0),
+            vec3(-1.0, 1.0, -1.0),
+            vec3(1.0, 1.0, 1.0),
+        ];
+        k.iter()
+            .map(|k| k * self.root.distance(p + k * h))
+            .fold(Vector3::new(0.0, 0.0, 0.0), |acc, x| acc + x)
+            .normalize()
+    }
+}
+
+impl HittableTrait for Sdf {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        let origin = ray.origin - self.position.to_vec();
+        let local_ray = ray.moved(origin, ray.direction);
+        let (t_start, t_end) = match self.root.bounding_box() {
+            Some(bbox) => bbox.hit_range(&local_ray, min_dist, max_dist)?,
+            None => (min_dist, max_dist.min(MAX_DISTANCE)),
+        };
+        let lipschitz = self.root.lipschitz();
+
+        let mut t = t_start;
+        let p = local_ray.at(t).to_vec();
+        let distance = self.root.distance(p);
+        // Rays leaving the surface they were scattered from have to get clear of it first.
+        let mut leaving = distance.abs() < EPSILON;
+        let sign = if leaving {
+            self.normal_at(p).dot(ray.direction).signum()
+        } else {
+            distance.signum()
+        };
+
+        for _ in 0..self.max_steps {
+            let p = local_ray.at(t).to_vec();
+            let distance = sign * self.root.distance(p);
+            if leaving {
+                leaving = distance < EPSILON;
+            } else if distance < EPSILON {
+                let normal = self.normal_at(p);
+                return Some(HitRecord::new(
+                    ray,
+                    t,
+                    normal,
+                    Sphere::get_uv(&normal),
+                    self.material_id,
+                ));
+            }
+            t += distance.abs().max(EPSILON) / lipschitz;
+            if t > t_end {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.root
+            .bounding_box()
+            .map(|bbox| bbox.translated(self.position.to_vec()))
+    }
+
+    fn get_position(&self) -> Point3 {
+        self.position
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.position = c;
+    }
+
+    fn name(&self) -> &'static str {
+        "SDF"
+    }
+}
+
+impl HasMaterial for Sdf {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirrored_scale_keeps_inside_negative() {
+        let node = SdfNode::Sphere { radius: 1.0 }
+            .translate(vec3(1.0, 0.0, 0.0))
+            .scale(-2.0);
+        // The sphere now sits around (-2, 0, 0) with radius 2
+        assert!((node.distance(vec3(-2.0, 0.0, 0.0)) + 2.0).abs() < 1e-9);
+        assert!((node.distance(vec3(-6.0, 0.0, 0.0)) - 2.0).abs() < 1e-9);
+        let bbox = node.bounding_box().unwrap();
+        assert!((bbox.min - point3(-4.0, -2.0, -2.0)).magnitude() < 1e-9);
+        assert!((bbox.max - point3(0.0, 2.0, 2.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn mandelbulb_distance_is_finite_at_the_centre() {
+        assert_eq!(mandelbulb_distance(vec3(0.0, 0.0, 0.0), 8.0, 10), 0.0);
+        let near = mandelbulb_distance(vec3(1e-300, 0.0, 0.0), 8.0, 10);
+        assert!(near.is_finite() && near.abs() < 1e-9);
+    }
+}
@@ -1,4 +1,4 @@
-use cgmath::InnerSpace;
+use cgmath::{vec3, InnerSpace};
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -6,7 +6,7 @@ use crate::{
     vec_repo::Id,
 };
 
-use super::{HitRecord, HittableTrait};
+use super::{aabb::Aabb, HasMaterial, HitRecord, HittableTrait};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Sphere {
@@ -31,6 +31,16 @@ impl Sphere {
         let v = normal.y.asin() / pi + 0.5;
         (u, v)
     }
+
+    /// Derivatives of the point at `normal` along `get_uv`, for a sphere of `radius`.
+    pub(super) fn get_tangents(normal: &Vector3, radius: f64) -> (Vector3, Vector3) {
+        let pi = std::f64::consts::PI;
+        let rho = (normal.x * normal.x + normal.z * normal.z).sqrt().max(1e-9);
+        let dpdu = vec3(normal.z, 0.0, -normal.x) * (2.0 * pi * radius);
+        let dpdv =
+            vec3(-normal.y * normal.x / rho, rho, -normal.y * normal.z / rho) * (pi * radius);
+        (dpdu, dpdv)
+    }
 }
 
 impl HittableTrait for Sphere {
@@ -53,12 +63,23 @@ impl HittableTrait for Sphere {
         }
         let point = ray.at(root);
         let outward_normal = (point - self.center) / self.radius;
-        Some(HitRecord::new(
-            ray,
-            root,
-            outward_normal,
-            Sphere::get_uv(&outward_normal),
-            self.material_id,
+        let (dpdu, dpdv) = Sphere::get_tangents(&outward_normal, self.radius);
+        Some(
+            HitRecord::new(
+                ray,
+                root,
+                outward_normal,
+                Sphere::get_uv(&outward_normal),
+                self.material_id,
+            )
+            .with_tangents(dpdu, dpdv),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::around(
+            self.center,
+            vec3(self.radius, self.radius, self.radius),
         ))
     }
 
@@ -73,3 +94,13 @@ impl HittableTrait for Sphere {
         "Sphere"
     }
 }
+
+impl HasMaterial for Sphere {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
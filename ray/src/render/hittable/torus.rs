@@ -0,0 +1,330 @@
+use cgmath::{vec3, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{material::Material, onb::Onb, Point3, Ray, Vector3},
+    vec_repo::Id,
+};
+
+use super::{
+    aabb::Aabb,
+    disk::{polar_dpdu, polar_uv},
+    HasMaterial, HitRecord, HittableTrait,
+};
+
+/// Torus around `axis`, `major_radius` is measured to the center of the tube.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Torus {
+    pub center: Point3,
+    pub axis: Vector3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    material_id: Id<Material>,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point3,
+        axis: Vector3,
+        major_radius: f64,
+        minor_radius: f64,
+        material_id: Id<Material>,
+    ) -> Self {
+        Self {
+            center,
+            axis: axis.normalize(),
+            major_radius,
+            minor_radius,
+            material_id,
+        }
+    }
+}
+
+impl HittableTrait for Torus {
+    fn hit_bounded(&self, ray: &Ray, min_dist: f64, max_dist: f64) -> Option<HitRecord> {
+        let onb = Onb::from_w(self.axis);
+        let o = onb.to_local(ray.origin - self.center);
+        let d = onb.to_local(ray.direction);
+        let (big_r, small_r) = (self.major_radius, self.minor_radius);
+
+        // Start from the bounding sphere, the quartic loses precision far from the torus.
+        let bound = big_r + small_r;
+        let half_b = o.dot(d);
+        let c = o.magnitude2() - bound * bound;
+        let discriminant = half_b * half_b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let t_near = (-half_b - discriminant.sqrt()).max(0.0);
+        let o = o + d * t_near;
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2), with |d| = 1
+        let r2 = big_r * big_r;
+        let e = o.magnitude2() + r2 - small_r * small_r;
+        let f = o.dot(d);
+        let coeffs = [
+            e * e - 4.0 * r2 * (o.x * o.x + o.y * o.y),
+            4.0 * f * e - 8.0 * r2 * (o.x * d.x + o.y * d.y),
+            4.0 * f * f + 2.0 * e - 4.0 * r2 * (d.x * d.x + d.y * d.y),
+            4.0 * f,
+            1.0,
+        ];
+        let (roots, count) = solve_quartic(coeffs);
+
+        let t = roots[..count]
+            .iter()
+            .map(|t| polish_root(&coeffs, *t) + t_near)
+            .filter(|t| *t >= min_dist && *t <= max_dist)
+            .min_by(|a, b| a.total_cmp(b))?;
+
+        let p = o + d * (t - t_near);
+        let ring = vec3(p.x, p.y, 0.0).normalize() * big_r;
+        let normal = (p - ring).normalize();
+        let (u, dist) = polar_uv(p.x, p.y);
+        let pi = std::f64::consts::PI;
+        let v = p.z.atan2(dist - big_r) / (2.0 * pi) + 0.5;
+        let radial = vec3(p.x, p.y, 0.0) / dist.max(1e-12);
+        let dpdv = (vec3(0.0, 0.0, dist - big_r) - radial * p.z) * (2.0 * pi);
+        Some(
+            HitRecord::new(ray, t, onb.to_world(normal), (u, v), self.material_id)
+                .with_tangents(onb.to_world(polar_dpdu(p)), onb.to_world(dpdv)),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let n = self.axis.normalize();
+        let extent = n.map(|x| self.major_radius * (1.0 - x * x).max(0.0).sqrt())
+            + vec3(1.0, 1.0, 1.0) * self.minor_radius;
+        Some(Aabb::around(self.center, extent))
+    }
+
+    fn get_position(&self) -> Point3 {
+        self.center
+    }
+
+    fn set_position(&mut self, c: Point3) {
+        self.center = c;
+    }
+
+    fn name(&self) -> &'static str {
+        "Torus"
+    }
+}
+
+impl HasMaterial for Torus {
+    fn material_id(&self) -> Id<Material> {
+        self.material_id
+    }
+
+    fn set_material_id(&mut self, id: Id<Material>) {
+        self.material_id = id;
+    }
+}
+
+// Polynomial solvers after J. Schwarze, "Cubic and Quartic Roots", Graphics Gems I.
+// Coefficients are given from the constant term upwards.
+
+const EPS: f64 = 1e-9;
+
+fn is_zero(x: f64) -> bool {
+    x.abs() < EPS
+}
+
+fn solve_quadratic(c: [f64; 3]) -> ([f64; 2], usize) {
+    let p = c[1] / (2.0 * c[2]);
+    let q = c[0] / c[2];
+    let d = p * p - q;
+    if is_zero(d) {
+        ([-p, 0.0], 1)
+    } else if d < 0.0 {
+        ([0.0; 2], 0)
+    } else {
+        let sqrt_d = d.sqrt();
+        ([sqrt_d - p, -sqrt_d - p], 2)
+    }
+}
+
+fn solve_cubic(c: [f64; 4]) -> ([f64; 3], usize) {
+    let a = c[2] / c[3];
+    let b = c[1] / c[3];
+    let c = c[0] / c[3];
+
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+
+    let (mut roots, count) = if is_zero(d) {
+        if is_zero(q) {
+            ([0.0; 3], 1)
+        } else {
+            let u = (-q).cbrt();
+            ([2.0 * u, -u, 0.0], 2)
+        }
+    } else if d < 0.0 {
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        let third = std::f64::consts::PI / 3.0;
+        (
+            [
+                t * phi.cos(),
+                -t * (phi + third).cos(),
+                -t * (phi - third).cos(),
+            ],
+            3,
+        )
+    } else {
+        let sqrt_d = d.sqrt();
+        let u = (sqrt_d - q).cbrt();
+        let v = -(sqrt_d + q).cbrt();
+        ([u + v, 0.0, 0.0], 1)
+    };
+
+    let sub = a / 3.0;
+    roots.iter_mut().for_each(|r| *r -= sub);
+    (roots, count)
+}
+
+fn solve_quartic(c: [f64; 5]) -> ([f64; 4], usize) {
+    let a = c[3] / c[4];
+    let b = c[2] / c[4];
+    let cc = c[1] / c[4];
+    let d = c[0] / c[4];
+
+    // y^4 + p y^2 + q y + r = 0, x = y - a/4
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + cc;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * cc / 4.0 + d;
+
+    let mut roots = [0.0; 4];
+    let count;
+    if is_zero(r) {
+        let (cubic, n) = solve_cubic([q, p, 0.0, 1.0]);
+        roots[..n].copy_from_slice(&cubic[..n]);
+        roots[n] = 0.0;
+        count = n + 1;
+    } else {
+        let (cubic, _) = solve_cubic([r * p / 2.0 - q * q / 8.0, -r, -p / 2.0, 1.0]);
+        let z = cubic[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if is_zero(u) {
+            0.0
+        } else if u > 0.0 {
+            u.sqrt()
+        } else {
+            return (roots, 0);
+        };
+        let v = if is_zero(v) {
+            0.0
+        } else if v > 0.0 {
+            v.sqrt()
+        } else {
+            return (roots, 0);
+        };
+
+        let (first, n1) = solve_quadratic([z - u, if q < 0.0 { -v } else { v }, 1.0]);
+        let (second, n2) = solve_quadratic([z + u, if q < 0.0 { v } else { -v }, 1.0]);
+        roots[..n1].copy_from_slice(&first[..n1]);
+        roots[n1..n1 + n2].copy_from_slice(&second[..n2]);
+        count = n1 + n2;
+    }
+
+    let sub = a / 4.0;
+    roots[..count].iter_mut().for_each(|r| *r -= sub);
+    (roots, count)
+}
+
+/// A few Newton steps to win back precision lost in the closed-form solution.
+fn polish_root(c: &[f64; 5], mut x: f64) -> f64 {
+    for _ in 0..2 {
+        let f = (((c[4] * x + c[3]) * x + c[2]) * x + c[1]) * x + c[0];
+        let df = ((4.0 * c[4] * x + 3.0 * c[3]) * x + 2.0 * c[2]) * x + c[1];
+        if df.abs() < EPS {
+            break;
+        }
+        x -= f / df;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{point3, vec3};
+
+    use super::*;
+
+    fn sorted_roots(c: [f64; 5]) -> Vec<f64> {
+        let (roots, count) = solve_quartic(c);
+        let mut roots: Vec<_> = roots[..count].iter().map(|x| polish_root(&c, *x)).collect();
+        roots.sort_by(|a, b| a.total_cmp(b));
+        roots
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn quartic_with_four_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_close(
+            &sorted_roots([24.0, -50.0, 35.0, -10.0, 1.0]),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+    }
+
+    #[test]
+    fn quartic_with_two_roots() {
+        // (x^2 + 1)(x - 1)(x - 2)
+        assert_close(&sorted_roots([2.0, -3.0, 3.0, -3.0, 1.0]), &[1.0, 2.0]);
+    }
+
+    #[test]
+    fn quartic_without_roots() {
+        assert!(sorted_roots([1.0, 0.0, 0.0, 0.0, 1.0]).is_empty());
+    }
+
+    #[test]
+    fn quartic_with_zero_root() {
+        // x (x + 1)(x - 1)(x - 2)
+        assert_close(
+            &sorted_roots([0.0, 2.0, -1.0, -2.0, 1.0]),
+            &[-1.0, 0.0, 1.0, 2.0],
+        );
+    }
+
+    #[test]
+    fn ray_hits_outside_of_tube() {
+        let torus = Torus::new(
+            point3(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            2.0,
+            0.5,
+            Id::default(),
+        );
+        let ray = Ray::new(point3(10.0, 0.0, 0.0), vec3(-1.0, 0.0, 0.0), 0.0);
+        let hit = torus.hit_bounded(&ray, 1e-3, f64::INFINITY).unwrap();
+        assert!((hit.distance - 7.5).abs() < 1e-6);
+        assert!((hit.normal - vec3(1.0, 0.0, 0.0)).magnitude() < 1e-6);
+    }
+
+    #[test]
+    fn ray_through_hole_misses() {
+        let torus = Torus::new(
+            point3(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            2.0,
+            0.5,
+            Id::default(),
+        );
+        let ray = Ray::new(point3(0.0, 0.0, 10.0), vec3(0.0, 0.0, -1.0), 0.0);
+        assert!(torus.hit_bounded(&ray, 1e-3, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,315 @@
+use std::f64::consts::PI;
+
+use cgmath::{vec3, InnerSpace};
+use enum_dispatch::enum_dispatch;
+use serde::{Deserialize, Serialize};
+
+use super::{onb::Onb, random_f64, Colour, Point3, Ray, Vector3};
+
+/// Light reaching a point from a direction picked by `LightTrait::sample`.
+pub struct LightSample {
+    pub direction: Vector3,
+    /// Distance to the light, infinite for the sun.
+    pub distance: f64,
+    pub radiance: Colour,
+    /// Solid angle density of `direction`, `None` for lights reached from a single direction.
+    pub pdf: Option<f64>,
+}
+
+#[enum_dispatch]
+pub trait LightTrait {
+    fn name(&self) -> &'static str;
+    fn sample(&self, point: Point3) -> Option<LightSample>;
+    /// Density with which `sample` picks `direction` from `point`, zero for point-like lights.
+    fn pdf(&self, _point: Point3, _direction: Vector3) -> f64 {
+        0.0
+    }
+    /// Radiance seen along `ray` if it reaches the light within `max_dist`.
+    fn hit(&self, _ray: &Ray, _max_dist: f64) -> Option<Colour> {
+        None
+    }
+}
+
+#[enum_dispatch(LightTrait)]
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Light {
+    PointLight,
+    SpotLight,
+    SunLight,
+    SphereLight,
+    RectLight,
+}
+
+/// Direction around `axis` picked uniformly from the cone with the given cosine.
+fn sample_cone(axis: Vector3, cos_max: f64) -> Vector3 {
+    let cos = 1.0 - random_f64(0.0, 1.0) * (1.0 - cos_max);
+    let sin = (1.0 - cos * cos).max(0.0).sqrt();
+    let phi = 2.0 * PI * random_f64(0.0, 1.0);
+    Onb::from_w(axis).to_world(vec3(sin * phi.cos(), sin * phi.sin(), cos))
+}
+
+fn cone_solid_angle(cos_max: f64) -> f64 {
+    2.0 * PI * (1.0 - cos_max)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PointLight {
+    pub position: Point3,
+    pub colour: Colour,
+    pub intensity: f32,
+}
+
+impl LightTrait for PointLight {
+    fn name(&self) -> &'static str {
+        "Point Light"
+    }
+
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance = offset.magnitude();
+        Some(LightSample {
+            direction: offset / distance,
+            distance,
+            radiance: self.colour * self.intensity / (distance * distance) as f32,
+            pdf: None,
+        })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpotLight {
+    pub position: Point3,
+    pub direction: Vector3,
+    pub colour: Colour,
+    pub intensity: f32,
+    /// Angle between the axis and the edge of the cone, in degrees.
+    pub angle: f64,
+    /// Part of the cone, from its edge inwards, over which the light fades out.
+    pub falloff: f64,
+}
+
+impl LightTrait for SpotLight {
+    fn name(&self) -> &'static str {
+        "Spotlight"
+    }
+
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let offset = self.position - point;
+        let distance = offset.magnitude();
+        let direction = offset / distance;
+
+        let cos = (-direction).dot(self.direction.normalize());
+        let cos_outer = self.angle.to_radians().cos();
+        let cos_inner = (self.angle * (1.0 - self.falloff.clamp(0.0, 1.0)))
+            .to_radians()
+            .cos();
+        if cos <= cos_outer {
+            return None;
+        }
+        let t = ((cos - cos_outer) / (cos_inner - cos_outer).max(1e-9)).min(1.0);
+        let edge = t * t * (3.0 - 2.0 * t);
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.colour * (self.intensity * edge as f32) / (distance * distance) as f32,
+            pdf: None,
+        })
+    }
+}
+
+/// Light from infinitely far away, like the sun.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SunLight {
+    /// Towards the sun.
+    pub direction: Vector3,
+    pub colour: Colour,
+    /// Irradiance on a surface facing the sun.
+    pub strength: f32,
+    /// Size of the sun disc in degrees, zero for perfectly sharp shadows.
+    pub angular_diameter: f64,
+}
+
+impl SunLight {
+    fn cos_max(&self) -> f64 {
+        (self.angular_diameter / 2.0).to_radians().cos()
+    }
+}
+
+impl LightTrait for SunLight {
+    fn name(&self) -> &'static str {
+        "Sun"
+    }
+
+    fn sample(&self, _point: Point3) -> Option<LightSample> {
+        let axis = self.direction.normalize();
+        if self.angular_diameter <= 0.0 {
+            return Some(LightSample {
+                direction: axis,
+                distance: f64::INFINITY,
+                radiance: self.colour * self.strength,
+                pdf: None,
+            });
+        }
+        let solid_angle = cone_solid_angle(self.cos_max());
+        Some(LightSample {
+            direction: sample_cone(axis, self.cos_max()),
+            distance: f64::INFINITY,
+            radiance: self.colour * (self.strength / solid_angle as f32),
+            pdf: Some(1.0 / solid_angle),
+        })
+    }
+
+    fn pdf(&self, _point: Point3, direction: Vector3) -> f64 {
+        if self.angular_diameter <= 0.0
+            || direction.normalize().dot(self.direction.normalize()) < self.cos_max()
+        {
+            return 0.0;
+        }
+        1.0 / cone_solid_angle(self.cos_max())
+    }
+
+    fn hit(&self, ray: &Ray, max_dist: f64) -> Option<Colour> {
+        let pdf = self.pdf(ray.origin, ray.direction);
+        (max_dist == f64::INFINITY && pdf > 0.0).then(|| self.colour * (self.strength * pdf as f32))
+    }
+}
+
+/// Glowing ball, sampled by the cone it covers as seen from the lit point.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SphereLight {
+    pub center: Point3,
+    pub radius: f64,
+    pub colour: Colour,
+    /// Radiance leaving the surface.
+    pub strength: f32,
+}
+
+impl SphereLight {
+    /// Cosine of the half angle covered from `point`, `None` from inside.
+    fn cos_max(&self, point: Point3) -> Option<f64> {
+        let distance2 = (self.center - point).magnitude2();
+        let radius2 = self.radius * self.radius;
+        (distance2 > radius2).then(|| (1.0 - radius2 / distance2).sqrt())
+    }
+
+    fn intersect(&self, origin: Point3, direction: Vector3) -> Option<f64> {
+        let oc = origin - self.center;
+        let half_b = oc.dot(direction);
+        let c = oc.magnitude2() - self.radius * self.radius;
+        let discriminant = half_b * half_b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let t = -half_b - discriminant.sqrt();
+        (t > 0.0).then_some(t)
+    }
+}
+
+impl LightTrait for SphereLight {
+    fn name(&self) -> &'static str {
+        "Sphere Light"
+    }
+
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let cos_max = self.cos_max(point)?;
+        let direction = sample_cone((self.center - point).normalize(), cos_max);
+        let distance = self
+            .intersect(point, direction)
+            .unwrap_or_else(|| (self.center - point).magnitude());
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.colour * self.strength,
+            pdf: Some(1.0 / cone_solid_angle(cos_max)),
+        })
+    }
+
+    fn pdf(&self, point: Point3, direction: Vector3) -> f64 {
+        match self.cos_max(point) {
+            Some(cos_max) if direction.dot((self.center - point).normalize()) >= cos_max => {
+                1.0 / cone_solid_angle(cos_max)
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn hit(&self, ray: &Ray, max_dist: f64) -> Option<Colour> {
+        self.intersect(ray.origin, ray.direction)
+            .filter(|&t| t < max_dist)
+            .map(|_| self.colour * self.strength)
+    }
+}
+
+/// Parallelogram spanned by `u` and `v` from `corner`, emitting towards `u × v`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RectLight {
+    pub corner: Point3,
+    pub u: Vector3,
+    pub v: Vector3,
+    pub colour: Colour,
+    /// Radiance leaving the surface.
+    pub strength: f32,
+    pub two_sided: bool,
+}
+
+impl RectLight {
+    /// Distance along `direction` and the cosine at the light, when the lit side is hit.
+    fn intersect(&self, origin: Point3, direction: Vector3) -> Option<(f64, f64)> {
+        let n = self.u.cross(self.v);
+        let denom = n.dot(direction);
+        if denom.abs() < 1e-12 || (denom > 0.0 && !self.two_sided) {
+            return None;
+        }
+        let t = n.dot(self.corner - origin) / denom;
+        if t <= 0.0 {
+            return None;
+        }
+        let planar = origin + direction * t - self.corner;
+        let w = n / n.dot(n);
+        let alpha = w.dot(planar.cross(self.v));
+        let beta = w.dot(self.u.cross(planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        Some((t, denom.abs() / n.magnitude()))
+    }
+
+    fn area(&self) -> f64 {
+        self.u.cross(self.v).magnitude()
+    }
+}
+
+impl LightTrait for RectLight {
+    fn name(&self) -> &'static str {
+        "Rect Light"
+    }
+
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let target = self.corner + self.u * random_f64(0.0, 1.0) + self.v * random_f64(0.0, 1.0);
+        let offset = target - point;
+        let distance = offset.magnitude();
+        let direction = offset / distance;
+        let n = self.u.cross(self.v).normalize();
+        let cos = -direction.dot(n);
+        if cos.abs() < 1e-9 || (cos < 0.0 && !self.two_sided) {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.colour * self.strength,
+            pdf: Some(distance * distance / (self.area() * cos.abs())),
+        })
+    }
+
+    fn pdf(&self, point: Point3, direction: Vector3) -> f64 {
+        self.intersect(point, direction)
+            .map_or(0.0, |(t, cos)| t * t / (self.area() * cos))
+    }
+
+    fn hit(&self, ray: &Ray, max_dist: f64) -> Option<Colour> {
+        self.intersect(ray.origin, ray.direction)
+            .filter(|&(t, _)| t < max_dist)
+            .map(|_| self.colour * self.strength)
+    }
+}
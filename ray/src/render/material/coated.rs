@@ -0,0 +1,185 @@
+use cgmath::{ElementWise, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{
+        hittable::HitRecord,
+        microfacet::{fresnel_dielectric, reflect, Ggx},
+        onb::Onb,
+        random_f64,
+        texture::Texture,
+        Colour, Ray, Vector3,
+    },
+    vec_repo::{Id, VecRepo},
+};
+
+use super::{layer, Material, MaterialTrait, ScatterRecord};
+
+/// Thin dielectric layer, like varnish or lacquer, over another material.
+/// Light reaching the base loses what the coat reflects and what it absorbs on the way.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Coated {
+    pub base: Id<Material>,
+    pub refractive_index: f64,
+    pub roughness: f64,
+    /// Colour of white light after crossing the coat straight down and back up,
+    /// when `thickness` is one.
+    pub absorption: Colour,
+    pub thickness: f64,
+}
+
+impl Coated {
+    pub fn new(base: Id<Material>) -> Self {
+        Self {
+            base,
+            refractive_index: 1.5,
+            roughness: 0.0,
+            absorption: Colour::new(1.0, 1.0, 1.0),
+            thickness: 0.0,
+        }
+    }
+
+    /// Share of light crossing the coat down to the base along `wo` and back along `wi`.
+    fn transmittance(&self, wo: Vector3, wi: Vector3) -> Colour {
+        let eta = self.refractive_index;
+        let entering = 1.0 - fresnel_dielectric(wo.z.abs(), eta);
+        let leaving = 1.0 - fresnel_dielectric(wi.z.abs(), eta);
+        // Path length inside the coat relative to going straight down and back up
+        let refracted_cos = |cos: f64| (1.0 - (1.0 - cos * cos) / (eta * eta)).sqrt();
+        let length = 0.5 * (1.0 / refracted_cos(wo.z.abs()) + 1.0 / refracted_cos(wi.z.abs()));
+        let absorbed = self
+            .absorption
+            .map(|c| c.clamp(0.0, 1.0).powf((self.thickness * length) as f32));
+        absorbed * (entering * leaving) as f32
+    }
+
+    /// Chance of sampling the coat rather than the base.
+    fn coat_chance(&self, wo: Vector3) -> f64 {
+        fresnel_dielectric(wo.z, self.refractive_index)
+    }
+}
+
+impl MaterialTrait for Coated {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        let onb = Onb::from_w(hit.normal);
+        let wo = onb.to_local(-ray.direction);
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let ggx = Ggx::new(self.roughness, 0.0);
+        let chance = self.coat_chance(wo);
+
+        let direction = if random_f64(0.0, 1.0) < chance {
+            if ggx.is_smooth() {
+                // The coat is picked as often as it reflects
+                return Some(ScatterRecord {
+                    ray: Ray::new(
+                        hit.point,
+                        onb.to_world(reflect(wo, Vector3::unit_z())),
+                        ray.time,
+                    ),
+                    attenuation: Colour::new(1.0, 1.0, 1.0),
+                    pdf: None,
+                });
+            }
+            onb.to_world(reflect(wo, ggx.sample_visible_normal(wo)))
+        } else {
+            let scattered = layer(materials, self.base).scatter(ray, hit, materials, textures)?;
+            if scattered.pdf.is_none() {
+                let wi = onb.to_local(scattered.ray.direction);
+                let weight = self.transmittance(wo, wi) / (1.0 - chance) as f32;
+                return Some(ScatterRecord {
+                    attenuation: scattered.attenuation.mul_element_wise(weight),
+                    ..scattered
+                });
+            }
+            scattered.ray.direction
+        };
+
+        let pdf = self.pdf(ray, hit, direction, materials, textures);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord {
+            ray: Ray::new(hit.point, direction, ray.time),
+            attenuation: self.eval(ray, hit, direction, materials, textures) / pdf as f32,
+            pdf: Some(pdf),
+        })
+    }
+
+    fn eval(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let onb = Onb::from_w(hit.normal);
+        let (wo, wi) = (onb.to_local(-ray.direction), onb.to_local(direction));
+        if wo.z <= 0.0 {
+            return Colour::new(0.0, 0.0, 0.0);
+        }
+        let base = layer(materials, self.base).eval(ray, hit, direction, materials, textures);
+        let mut value = base.mul_element_wise(self.transmittance(wo, wi));
+
+        let ggx = Ggx::new(self.roughness, 0.0);
+        if !ggx.is_smooth() && wi.z > 0.0 {
+            let m = (wo + wi).normalize();
+            let fresnel = fresnel_dielectric(wo.dot(m), self.refractive_index);
+            let coat = fresnel * ggx.d(m) * ggx.g(wo, wi) / (4.0 * wo.z);
+            value += Colour::new(1.0, 1.0, 1.0) * coat as f32;
+        }
+        value
+    }
+
+    fn pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        let onb = Onb::from_w(hit.normal);
+        let (wo, wi) = (onb.to_local(-ray.direction), onb.to_local(direction));
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        let chance = self.coat_chance(wo);
+        let base = layer(materials, self.base).pdf(ray, hit, direction, materials, textures);
+
+        let ggx = Ggx::new(self.roughness, 0.0);
+        let coat = if !ggx.is_smooth() && wi.z > 0.0 {
+            let m = (wo + wi).normalize();
+            ggx.visible_normal_pdf(wo, m) / (4.0 * wo.dot(m))
+        } else {
+            0.0
+        };
+        chance * coat + (1.0 - chance) * base
+    }
+
+    fn emit(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        layer(materials, self.base).emit(hit, materials, textures)
+    }
+
+    fn opacity(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        layer(materials, self.base).opacity(hit, materials, textures)
+    }
+}
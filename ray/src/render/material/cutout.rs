@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{hittable::HitRecord, texture::Texture, Colour, Ray, Vector3},
+    vec_repo::{Id, VecRepo},
+};
+
+use super::{layer, Material, MaterialTrait, ScatterRecord};
+
+/// What part of `Cutout::mask` says how solid the surface is.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MaskChannel {
+    /// The alpha channel of an image, as painted for leaves, fences or decals.
+    Alpha,
+    /// Brightness, for black and white masks.
+    Luminance,
+}
+
+/// Another material with holes in it, wherever `mask` is see-through.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Cutout {
+    pub base: Id<Material>,
+    pub mask: Id<Texture>,
+    pub channel: MaskChannel,
+}
+
+impl Cutout {
+    pub fn new(base: Id<Material>, mask: Id<Texture>) -> Self {
+        Self {
+            base,
+            mask,
+            channel: MaskChannel::Alpha,
+        }
+    }
+}
+
+impl MaterialTrait for Cutout {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        layer(materials, self.base).scatter(ray, hit, materials, textures)
+    }
+
+    fn eval(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        layer(materials, self.base).eval(ray, hit, direction, materials, textures)
+    }
+
+    fn pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        layer(materials, self.base).pdf(ray, hit, direction, materials, textures)
+    }
+
+    fn emit(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        layer(materials, self.base).emit(hit, materials, textures)
+    }
+
+    fn opacity(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        let mask = textures.get(self.mask);
+        let coverage = match self.channel {
+            MaskChannel::Alpha => mask.alpha_at(hit.into(), textures),
+            MaskChannel::Luminance => mask.value_at(hit.into(), textures),
+        };
+        coverage.clamp(0.0, 1.0) * layer(materials, self.base).opacity(hit, materials, textures)
+    }
+}
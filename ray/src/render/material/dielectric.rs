@@ -0,0 +1,241 @@
+use cgmath::InnerSpace;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{
+        hittable::HitRecord,
+        microfacet::{fresnel_dielectric, reflect, refract, Ggx},
+        onb::Onb,
+        random_f64,
+        texture::Texture,
+        Colour, Ray, Vector3,
+    },
+    vec_repo::VecRepo,
+};
+
+use super::{Material, MaterialTrait, ScatterRecord};
+
+/// Glass and liquids, smooth or frosted with GGX microfacets.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Dielectric {
+    pub refractive_index: f64,
+    #[serde(default)]
+    pub roughness: f64,
+    /// Colour of white light after travelling `1 / density` inside the medium.
+    #[serde(default = "clear")]
+    pub absorption: Colour,
+    /// Zero makes the medium clear regardless of `absorption`.
+    #[serde(default)]
+    pub density: f64,
+    /// Variation of the index with wavelength, only visible in spectral mode.
+    #[serde(default)]
+    pub dispersion: Dispersion,
+}
+
+/// Wavelength dependent index of refraction, wavelengths in micrometres.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Dispersion {
+    #[default]
+    None,
+    /// `n = A + B / λ²`, with `A` chosen so the index at the sodium D line is `refractive_index`.
+    Cauchy { b: f64 },
+    /// `n² = 1 + Σ Bᵢ λ² / (λ² - Cᵢ)`, replaces `refractive_index` entirely.
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    /// Sodium D line, where indices of refraction are usually quoted.
+    const D_LINE: f64 = 0.5893;
+
+    pub const BK7: Self = Self::Sellmeier {
+        b: [1.039_612_12, 0.231_792_344, 1.010_469_45],
+        c: [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+    };
+    pub const FUSED_SILICA: Self = Self::Sellmeier {
+        b: [0.696_166_3, 0.407_942_6, 0.897_479_4],
+        c: [0.004_679_148, 0.013_512_06, 97.934_0],
+    };
+    pub const DIAMOND: Self = Self::Sellmeier {
+        b: [0.3306, 4.3356, 0.0],
+        c: [0.030_625, 0.011_236, 0.0],
+    };
+
+    /// Index at `wavelength` in nanometres, `refractive_index` when there is none.
+    pub fn index(&self, refractive_index: f64, wavelength: Option<f64>) -> f64 {
+        let lambda = wavelength.map_or(Self::D_LINE, |nm| nm / 1000.0);
+        match *self {
+            Dispersion::None => refractive_index,
+            Dispersion::Cauchy { b } => {
+                refractive_index + b / (lambda * lambda) - b / (Self::D_LINE * Self::D_LINE)
+            }
+            Dispersion::Sellmeier { b, c } => {
+                let l2 = lambda * lambda;
+                let n2 = 1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>();
+                n2.max(1.0).sqrt()
+            }
+        }
+    }
+}
+
+fn clear() -> Colour {
+    Colour::new(1.0, 1.0, 1.0)
+}
+
+/// Local frame directions of one reflection or refraction off a rough surface.
+struct Lobe {
+    ggx: Ggx,
+    wo: Vector3,
+    wi: Vector3,
+    m: Vector3,
+    /// Change of variables from the microfacet normal to `wi`.
+    jacobian: f64,
+    /// Probability of reflecting or refracting, whichever `wi` did.
+    chance: f64,
+}
+
+impl Dielectric {
+    pub fn new(refractive_index: f64) -> Self {
+        Self {
+            refractive_index,
+            roughness: 0.0,
+            absorption: clear(),
+            density: 0.0,
+            dispersion: Dispersion::None,
+        }
+    }
+
+    /// Ratio of the index on the far side of the surface to the near side.
+    fn eta(&self, ray: &Ray, hit: &HitRecord) -> f64 {
+        let index = self
+            .dispersion
+            .index(self.refractive_index, ray.wavelength());
+        if hit.front_face {
+            index
+        } else {
+            1.0 / index
+        }
+    }
+
+    /// Beer–Lambert attenuation of the ray segment that ended at `hit`.
+    fn transmittance(&self, hit: &HitRecord) -> Colour {
+        if hit.front_face || self.density <= 0.0 {
+            return clear();
+        }
+        let length = (self.density * hit.distance) as f32;
+        self.absorption.map(|c| c.clamp(0.0, 1.0).powf(length))
+    }
+
+    /// Microfacet configuration scattering `ray` towards `direction`, `None` when smooth.
+    fn lobe(&self, ray: &Ray, hit: &HitRecord, direction: Vector3) -> Option<Lobe> {
+        let ggx = Ggx::new(self.roughness, 0.0);
+        let onb = Onb::from_w(hit.normal);
+        let (wo, wi) = (onb.to_local(-ray.direction), onb.to_local(direction));
+        if ggx.is_smooth() || wo.z <= 0.0 {
+            return None;
+        }
+        let eta = self.eta(ray, hit);
+        let (m, jacobian) = Self::half_vector(wo, wi, eta)?;
+        let fresnel = fresnel_dielectric(wo.dot(m), eta);
+        Some(Lobe {
+            ggx,
+            wo,
+            wi,
+            m,
+            jacobian,
+            chance: if wi.z > 0.0 { fresnel } else { 1.0 - fresnel },
+        })
+    }
+
+    /// Generalised half vector and the change of variables from it to `wi`,
+    /// `None` for configurations the microfacets can't produce.
+    fn half_vector(wo: Vector3, wi: Vector3, eta: f64) -> Option<(Vector3, f64)> {
+        if wi.z > 0.0 {
+            let m = (wo + wi).normalize();
+            return Some((m, 1.0 / (4.0 * wo.dot(m))));
+        }
+        let m = (wo + wi * eta).normalize();
+        let m = if m.z < 0.0 { -m } else { m };
+        if wo.dot(m) <= 0.0 || wi.dot(m) >= 0.0 {
+            return None;
+        }
+        let denom = (wi.dot(m) + wo.dot(m) / eta).powi(2);
+        Some((m, -wi.dot(m) / denom))
+    }
+}
+
+impl MaterialTrait for Dielectric {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        let onb = Onb::from_w(hit.normal);
+        let wo = onb.to_local(-ray.direction);
+        let eta = self.eta(ray, hit);
+        let ggx = Ggx::new(self.roughness, 0.0);
+        let smooth = ggx.is_smooth();
+
+        let m = if smooth {
+            Vector3::unit_z()
+        } else {
+            ggx.sample_visible_normal(wo)
+        };
+        let cos_om = wo.dot(m);
+        let fresnel = fresnel_dielectric(cos_om, eta);
+        let (wi, reflected) = match refract(wo, m, eta) {
+            Some(wi) if random_f64(0.0, 1.0) >= fresnel => (wi, false),
+            _ => (reflect(wo, m), true),
+        };
+        // Rough microfacets can send the ray to the wrong side of the surface
+        if reflected != (wi.z > 0.0) {
+            return None;
+        }
+
+        let (attenuation, pdf) = if smooth {
+            (self.transmittance(hit), None)
+        } else {
+            let (_, jacobian) = Self::half_vector(wo, wi, eta)?;
+            let weight = (ggx.g(wo, wi) / ggx.g1(wo)) as f32;
+            let chance = if reflected { fresnel } else { 1.0 - fresnel };
+            let pdf = chance * ggx.visible_normal_pdf(wo, m) * jacobian;
+            (self.transmittance(hit) * weight, Some(pdf))
+        };
+        Some(ScatterRecord {
+            ray: Ray::new(hit.point, onb.to_world(wi), ray.time),
+            attenuation,
+            pdf,
+        })
+    }
+
+    fn eval(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let Some(lobe) = self.lobe(ray, hit, direction) else {
+            return Colour::new(0.0, 0.0, 0.0);
+        };
+        let (ggx, wo, wi, m) = (lobe.ggx, lobe.wo, lobe.wi, lobe.m);
+        // Same as the scatter weight times its pdf
+        let value = lobe.chance * ggx.d(m) * ggx.g(wo, wi) * wo.dot(m) * lobe.jacobian / wo.z;
+        self.transmittance(hit) * value as f32
+    }
+
+    fn pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> f64 {
+        self.lobe(ray, hit, direction).map_or(0.0, |lobe| {
+            lobe.chance * lobe.ggx.visible_normal_pdf(lobe.wo, lobe.m) * lobe.jacobian
+        })
+    }
+}
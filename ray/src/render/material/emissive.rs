@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{hittable::HitRecord, texture::Texture, Colour, Ray, Vector3},
+    vec_repo::{Id, VecRepo},
+};
+
+use super::{layer, Material, MaterialTrait, ScatterRecord};
+
+/// Adds textured emission to another material, which keeps scattering as before.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Emissive {
+    pub base: Id<Material>,
+    pub emission: Id<Texture>,
+    pub strength: f32,
+    /// Emit from the back face too, otherwise only where the outward normal points.
+    pub two_sided: bool,
+}
+
+impl Emissive {
+    pub fn new(base: Id<Material>, emission: Id<Texture>, strength: f32) -> Self {
+        Self {
+            base,
+            emission,
+            strength,
+            two_sided: false,
+        }
+    }
+}
+
+impl MaterialTrait for Emissive {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        layer(materials, self.base).scatter(ray, hit, materials, textures)
+    }
+
+    fn eval(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        layer(materials, self.base).eval(ray, hit, direction, materials, textures)
+    }
+
+    fn pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        layer(materials, self.base).pdf(ray, hit, direction, materials, textures)
+    }
+
+    fn emit(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let base = layer(materials, self.base).emit(hit, materials, textures);
+        if !hit.front_face && !self.two_sided {
+            return base;
+        }
+        base + textures.get(self.emission).colour_at(hit.into(), textures) * self.strength
+    }
+}
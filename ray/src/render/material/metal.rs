@@ -0,0 +1,177 @@
+use cgmath::{ElementWise, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{
+        hittable::HitRecord,
+        microfacet::{fresnel_conductor, fresnel_schlick, multiple_scattering, reflect, Ggx},
+        onb::Onb,
+        texture::Texture,
+        Colour, Ray, Vector3,
+    },
+    vec_repo::{Id, VecRepo},
+};
+
+use super::{Material, MaterialTrait, ScatterRecord};
+
+/// Complex index of refraction `eta + i k`, sampled at red, green and blue wavelengths.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ComplexIor {
+    pub eta: Colour,
+    pub k: Colour,
+}
+
+impl ComplexIor {
+    pub const GOLD: Self = Self::new([0.143, 0.374, 1.442], [3.983, 2.385, 1.603]);
+    pub const SILVER: Self = Self::new([0.155, 0.117, 0.138], [4.828, 3.122, 2.147]);
+    pub const COPPER: Self = Self::new([0.200, 0.924, 1.102], [3.912, 2.452, 2.142]);
+    pub const ALUMINIUM: Self = Self::new([1.657, 0.880, 0.521], [9.224, 6.270, 4.837]);
+    pub const IRON: Self = Self::new([2.912, 2.950, 2.585], [3.077, 2.932, 2.767]);
+    pub const CHROMIUM: Self = Self::new([4.368, 2.910, 1.654], [5.200, 4.231, 3.755]);
+    pub const PLATINUM: Self = Self::new([2.376, 2.085, 1.845], [4.265, 3.715, 3.137]);
+
+    pub const PRESETS: [(&'static str, Self); 7] = [
+        ("Gold", Self::GOLD),
+        ("Silver", Self::SILVER),
+        ("Copper", Self::COPPER),
+        ("Aluminium", Self::ALUMINIUM),
+        ("Iron", Self::IRON),
+        ("Chromium", Self::CHROMIUM),
+        ("Platinum", Self::PLATINUM),
+    ];
+
+    const fn new(eta: [f32; 3], k: [f32; 3]) -> Self {
+        Self {
+            eta: Colour::new(eta[0], eta[1], eta[2]),
+            k: Colour::new(k[0], k[1], k[2]),
+        }
+    }
+}
+
+/// GGX microfacet conductor.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Metal {
+    /// Reflectance at normal incidence, used when `ior` is not set.
+    pub albedo: Id<Texture>,
+    pub roughness: f64,
+    /// Stretches highlights along the tangent, `0` is isotropic.
+    pub anisotropy: f64,
+    pub ior: Option<ComplexIor>,
+}
+
+impl Metal {
+    pub fn from_ior(ior: ComplexIor, roughness: f64) -> Self {
+        Self {
+            albedo: Id::default(),
+            roughness,
+            anisotropy: 0.0,
+            ior: Some(ior),
+        }
+    }
+
+    fn fresnel(&self, cos_i: f64, hit: &HitRecord, textures: &VecRepo<Texture>) -> Colour {
+        match self.ior {
+            Some(ComplexIor { eta, k }) => fresnel_conductor(cos_i, eta, k),
+            None => {
+                let f0 = textures.get(self.albedo).colour_at(hit.into(), textures);
+                fresnel_schlick(cos_i, f0)
+            }
+        }
+    }
+
+    /// Fresnel term scaled to account for light bouncing between microfacets.
+    fn reflectance(
+        &self,
+        cos_i: f64,
+        cos_o: f64,
+        hit: &HitRecord,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let f0 = self.fresnel(1.0, hit, textures);
+        self.fresnel(cos_i, hit, textures)
+            .mul_element_wise(multiple_scattering(self.roughness, cos_o, f0))
+    }
+
+    /// Shading frame with `u` along `dpdu`, the axis anisotropy stretches highlights on.
+    fn frame(hit: &HitRecord) -> Onb {
+        Onb::from_w_along(hit.normal, hit.dpdu)
+    }
+
+    fn local_directions(ray: &Ray, hit: &HitRecord, direction: Vector3) -> (Vector3, Vector3) {
+        let onb = Self::frame(hit);
+        (onb.to_local(-ray.direction), onb.to_local(direction))
+    }
+}
+
+impl MaterialTrait for Metal {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        _materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        let onb = Self::frame(hit);
+        let wo = onb.to_local(-ray.direction);
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let ggx = Ggx::new(self.roughness, self.anisotropy);
+        if ggx.is_smooth() {
+            let wi = reflect(wo, Vector3::unit_z());
+            return Some(ScatterRecord {
+                ray: Ray::new(hit.point, onb.to_world(wi), ray.time),
+                attenuation: self.fresnel(wo.z, hit, textures),
+                pdf: None,
+            });
+        }
+
+        let m = ggx.sample_visible_normal(wo);
+        let wi = reflect(wo, m);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        let cos_om = wo.dot(m);
+        let weight = ggx.g(wo, wi) / ggx.g1(wo);
+        Some(ScatterRecord {
+            ray: Ray::new(hit.point, onb.to_world(wi), ray.time),
+            attenuation: self.reflectance(cos_om, wo.z, hit, textures) * weight as f32,
+            pdf: Some(ggx.visible_normal_pdf(wo, m) / (4.0 * cos_om)),
+        })
+    }
+
+    fn eval(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        _materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let ggx = Ggx::new(self.roughness, self.anisotropy);
+        let (wo, wi) = Self::local_directions(ray, hit, direction);
+        if ggx.is_smooth() || wo.z <= 0.0 || wi.z <= 0.0 {
+            return Colour::new(0.0, 0.0, 0.0);
+        }
+        let m = (wo + wi).normalize();
+        let value = ggx.d(m) * ggx.g(wo, wi) / (4.0 * wo.z);
+        self.reflectance(wo.dot(m), wo.z, hit, textures) * value as f32
+    }
+
+    fn pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> f64 {
+        let ggx = Ggx::new(self.roughness, self.anisotropy);
+        let (wo, wi) = Self::local_directions(ray, hit, direction);
+        if ggx.is_smooth() || wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let m = (wo + wi).normalize();
+        ggx.visible_normal_pdf(wo, m) / (4.0 * wo.dot(m))
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{hittable::HitRecord, random_f64, texture::Texture, Colour, Ray, Vector3},
+    vec_repo::{Id, VecRepo},
+};
+
+use super::{layer, Material, MaterialTrait, ScalarInput, ScatterRecord};
+
+/// Blend of two materials, `factor` is the amount of `second`.
+/// A material must not end up mixing itself.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Mix {
+    pub first: Id<Material>,
+    pub second: Id<Material>,
+    pub factor: ScalarInput,
+}
+
+impl Mix {
+    fn factor(&self, hit: &HitRecord, textures: &VecRepo<Texture>) -> f64 {
+        self.factor.value_at(hit.into(), textures).clamp(0.0, 1.0)
+    }
+}
+
+impl MaterialTrait for Mix {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        let factor = self.factor(hit, textures);
+        let chosen = if random_f64(0.0, 1.0) < factor {
+            self.second
+        } else {
+            self.first
+        };
+        let scattered = layer(materials, chosen).scatter(ray, hit, materials, textures)?;
+        if scattered.pdf.is_none() {
+            // The chance of picking a specular material cancels out with its weight
+            return Some(scattered);
+        }
+        let direction = scattered.ray.direction;
+        let pdf = self.pdf(ray, hit, direction, materials, textures);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord {
+            attenuation: self.eval(ray, hit, direction, materials, textures) / pdf as f32,
+            pdf: Some(pdf),
+            ..scattered
+        })
+    }
+
+    fn eval(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let factor = self.factor(hit, textures) as f32;
+        let eval = |id| layer(materials, id).eval(ray, hit, direction, materials, textures);
+        eval(self.first) * (1.0 - factor) + eval(self.second) * factor
+    }
+
+    fn pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        let factor = self.factor(hit, textures);
+        let pdf = |id| layer(materials, id).pdf(ray, hit, direction, materials, textures);
+        pdf(self.first) * (1.0 - factor) + pdf(self.second) * factor
+    }
+
+    fn emit(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let factor = self.factor(hit, textures) as f32;
+        let emit = |id| layer(materials, id).emit(hit, materials, textures);
+        emit(self.first) * (1.0 - factor) + emit(self.second) * factor
+    }
+
+    fn opacity(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        let factor = self.factor(hit, textures);
+        let opacity = |id| layer(materials, id).opacity(hit, materials, textures);
+        opacity(self.first) * (1.0 - factor) + opacity(self.second) * factor
+    }
+}
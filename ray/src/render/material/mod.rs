@@ -0,0 +1,464 @@
+use std::{cell::Cell, f64::consts::PI};
+
+use cgmath::{InnerSpace, Zero};
+use enum_dispatch::enum_dispatch;
+use serde::{Deserialize, Serialize};
+
+use crate::vec_repo::{Id, VecRepo};
+
+use super::{
+    hittable::HitRecord,
+    random_vec_in_sphere,
+    texture::{TexCoord, Texture},
+    Colour, Ray, Vector3,
+};
+
+pub mod coated;
+pub mod cutout;
+pub mod dielectric;
+pub mod emissive;
+pub mod metal;
+pub mod mix;
+pub mod normal_map;
+pub mod principled;
+
+pub use coated::Coated;
+pub use cutout::{Cutout, MaskChannel};
+pub use dielectric::{Dielectric, Dispersion};
+pub use emissive::Emissive;
+pub use metal::{ComplexIor, Metal};
+pub use mix::Mix;
+pub use normal_map::{NormalMap, NormalMapKind};
+pub use principled::Principled;
+
+pub struct ScatterRecord {
+    pub ray: Ray,
+    /// BSDF times cosine divided by `pdf`.
+    pub attenuation: Colour,
+    /// Density the direction was sampled with, `None` for perfectly specular scattering.
+    pub pdf: Option<f64>,
+}
+
+#[enum_dispatch(MaterialTrait)]
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Material {
+    Lambertian,
+    Metal,
+    Dielectric,
+    DiffuseLight,
+    Isotropic,
+    Principled,
+    Mix,
+    Coated,
+    Emissive,
+    NormalMap,
+    Cutout,
+}
+
+impl Material {
+    /// Materials this one is built from, and the textures it reads itself.
+    pub fn references(&self) -> (Vec<Id<Material>>, Vec<Id<Texture>>) {
+        // Cheap, as materials hold nothing but ids and numbers
+        let mut material = self.clone();
+        let (materials, textures) = material.references_mut();
+        (
+            materials.into_iter().map(|id| *id).collect(),
+            textures.into_iter().map(|id| *id).collect(),
+        )
+    }
+
+    pub fn references_mut(&mut self) -> (Vec<&mut Id<Material>>, Vec<&mut Id<Texture>>) {
+        match self {
+            Material::Lambertian(m) => (vec![], vec![&mut m.albedo]),
+            Material::Metal(m) => (vec![], vec![&mut m.albedo]),
+            Material::Dielectric(_) => (vec![], vec![]),
+            Material::DiffuseLight(m) => (vec![], vec![&mut m.emit]),
+            Material::Isotropic(m) => (vec![], vec![&mut m.albedo]),
+            Material::Principled(m) => {
+                let mut textures = vec![&mut m.base_colour, &mut m.emission];
+                textures.extend(
+                    [
+                        &mut m.metallic,
+                        &mut m.roughness,
+                        &mut m.specular,
+                        &mut m.sheen,
+                        &mut m.clearcoat,
+                        &mut m.clearcoat_roughness,
+                        &mut m.transmission,
+                    ]
+                    .into_iter()
+                    .filter_map(ScalarInput::texture_mut),
+                );
+                (vec![], textures)
+            }
+            Material::Mix(m) => (
+                vec![&mut m.first, &mut m.second],
+                m.factor.texture_mut().into_iter().collect(),
+            ),
+            Material::Coated(m) => (vec![&mut m.base], vec![]),
+            Material::Emissive(m) => (vec![&mut m.base], vec![&mut m.emission]),
+            Material::NormalMap(m) => (vec![&mut m.base], vec![&mut m.texture]),
+            Material::Cutout(m) => (vec![&mut m.base], vec![&mut m.mask]),
+        }
+    }
+}
+
+/// Deeper than any sensible stack of materials built on one another, so that a
+/// cycle of them, which the editor won't make but a file might hold, can't
+/// overflow the stack.
+const MAX_LAYERS: u32 = 16;
+
+thread_local! {
+    /// How deep layers nest, and whether the call under way has gone past `MAX_LAYERS`.
+    static LAYERS: Cell<(u32, bool)> = const { Cell::new((0, false)) };
+}
+
+/// A material another one is built on. Calls through it give up, as if the
+/// surface absorbed everything, once layers nest past `MAX_LAYERS`, and so does
+/// the rest of that call, which a cycle of `Mix`es would otherwise branch out of.
+pub(super) struct Layer<'a>(&'a Material);
+
+pub(super) fn layer(materials: &VecRepo<Material>, id: Id<Material>) -> Layer<'_> {
+    Layer(materials.get(id))
+}
+
+impl Layer<'_> {
+    fn nested<R>(&self, fallback: R, f: impl FnOnce(&Material) -> R) -> R {
+        let (depth, cut) = LAYERS.get();
+        if cut || depth >= MAX_LAYERS {
+            LAYERS.set((depth, true));
+            return fallback;
+        }
+        LAYERS.set((depth + 1, false));
+        let result = f(self.0);
+        let (_, cut) = LAYERS.get();
+        // Back at the top, the next call starts afresh
+        LAYERS.set((depth, cut && depth > 0));
+        result
+    }
+}
+
+impl MaterialTrait for Layer<'_> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        self.nested(None, |m| m.scatter(ray, hit, materials, textures))
+    }
+
+    fn eval(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        self.nested(Colour::zero(), |m| {
+            m.eval(ray, hit, direction, materials, textures)
+        })
+    }
+
+    fn pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        self.nested(0.0, |m| m.pdf(ray, hit, direction, materials, textures))
+    }
+
+    fn emit(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        self.nested(Colour::zero(), |m| m.emit(hit, materials, textures))
+    }
+
+    fn opacity(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        self.nested(1.0, |m| m.opacity(hit, materials, textures))
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::from(Lambertian {
+            albedo: Id::default(),
+        })
+    }
+}
+
+#[enum_dispatch]
+pub trait MaterialTrait {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord>;
+    /// BSDF times cosine for light arriving from `direction` and leaving towards `ray` origin.
+    /// Zero for specular materials, which can only be sampled with `scatter`.
+    fn eval(
+        &self,
+        _ray: &Ray,
+        _hit: &HitRecord,
+        _direction: Vector3,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> Colour {
+        Colour::zero()
+    }
+    /// Density with which `scatter` picks `direction`.
+    fn pdf(
+        &self,
+        _ray: &Ray,
+        _hit: &HitRecord,
+        _direction: Vector3,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> f64 {
+        0.0
+    }
+    /// Light leaving the surface at `hit`, towards the side the normal faces.
+    fn emit(
+        &self,
+        _hit: &HitRecord,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> Colour {
+        Colour::zero()
+    }
+    /// Chance of the surface at `hit` being there at all, rays passing
+    /// straight through where it is cut away.
+    fn opacity(
+        &self,
+        _hit: &HitRecord,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> f64 {
+        1.0
+    }
+}
+
+/// Material parameter given directly or by the luminance of a texture.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScalarInput {
+    Constant(f64),
+    Texture(Id<Texture>),
+}
+
+impl ScalarInput {
+    pub fn value_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> f64 {
+        match *self {
+            ScalarInput::Constant(value) => value,
+            ScalarInput::Texture(id) => textures.get(id).value_at(at, textures),
+        }
+    }
+
+    fn texture_mut(&mut self) -> Option<&mut Id<Texture>> {
+        match self {
+            ScalarInput::Constant(_) => None,
+            ScalarInput::Texture(id) => Some(id),
+        }
+    }
+}
+
+impl From<f64> for ScalarInput {
+    fn from(value: f64) -> Self {
+        ScalarInput::Constant(value)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Lambertian {
+    pub albedo: Id<Texture>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DiffuseLight {
+    pub emit: Id<Texture>,
+    pub amplify: f32,
+    /// Emit from the back face too, otherwise only where the outward normal points.
+    #[serde(default = "two_sided_by_default")]
+    pub two_sided: bool,
+}
+
+/// Lights saved before `two_sided` existed emitted from both faces.
+fn two_sided_by_default() -> bool {
+    true
+}
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Isotropic {
+    pub albedo: Id<Texture>,
+}
+
+impl MaterialTrait for Lambertian {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        _materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        let scatter_dir = hit.normal + random_vec_in_sphere();
+        let scatter_dir = if scatter_dir.magnitude2() < 0.000001 {
+            hit.normal
+        } else {
+            scatter_dir.normalize()
+        };
+        Some(ScatterRecord {
+            ray: Ray::new(hit.point, scatter_dir, ray.time),
+            attenuation: textures.get(self.albedo).colour_at(hit.into(), textures),
+            pdf: Some(scatter_dir.dot(hit.normal).max(0.0) / PI),
+        })
+    }
+
+    fn eval(
+        &self,
+        _ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        _materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let cosine = direction.dot(hit.normal).max(0.0) / PI;
+        textures.get(self.albedo).colour_at(hit.into(), textures) * cosine as f32
+    }
+
+    fn pdf(
+        &self,
+        _ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> f64 {
+        direction.dot(hit.normal).max(0.0) / PI
+    }
+}
+
+impl MaterialTrait for DiffuseLight {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        _hit: &HitRecord,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        None
+    }
+    fn emit(
+        &self,
+        hit: &HitRecord,
+        _materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        if !hit.front_face && !self.two_sided {
+            return Colour::zero();
+        }
+        textures.get(self.emit).colour_at(hit.into(), textures) * self.amplify
+    }
+}
+
+impl MaterialTrait for Isotropic {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        _materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            ray: Ray::new(hit.point, random_vec_in_sphere(), ray.time),
+            attenuation: textures.get(self.albedo).colour_at(hit.into(), textures),
+            pdf: Some(1.0 / (4.0 * PI)),
+        })
+    }
+
+    fn eval(
+        &self,
+        _ray: &Ray,
+        hit: &HitRecord,
+        _direction: Vector3,
+        _materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        textures.get(self.albedo).colour_at(hit.into(), textures) / (4.0 * std::f32::consts::PI)
+    }
+
+    fn pdf(
+        &self,
+        _ray: &Ray,
+        _hit: &HitRecord,
+        _direction: Vector3,
+        _materials: &VecRepo<Material>,
+        _textures: &VecRepo<Texture>,
+    ) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{point3, vec3};
+
+    use super::*;
+
+    fn hit() -> HitRecord {
+        HitRecord {
+            point: point3(0.0, 0.0, 0.0),
+            normal: vec3(0.0, 1.0, 0.0),
+            dpdu: vec3(1.0, 0.0, 0.0),
+            dpdv: vec3(0.0, 0.0, 1.0),
+            distance: 1.0,
+            uv: (0.5, 0.5),
+            front_face: true,
+            material_id: Id::default(),
+            footprint: 0.0,
+        }
+    }
+
+    #[test]
+    fn cyclic_layers_give_up_instead_of_overflowing() {
+        let mut materials = VecRepo::<Material>::default();
+        let textures = VecRepo::<Texture>::default();
+        let mix = materials.insert(Material::default());
+        *materials.get_mut(mix).unwrap() = Mix {
+            first: mix,
+            second: mix,
+            factor: ScalarInput::Constant(0.5),
+        }
+        .into();
+        let emissive = materials.insert(Material::default());
+        *materials.get_mut(emissive).unwrap() = Emissive {
+            base: emissive,
+            emission: Id::default(),
+            strength: 1.0,
+            two_sided: false,
+        }
+        .into();
+
+        let ray = Ray::new(point3(0.0, 1.0, 0.0), vec3(0.0, -1.0, 0.0), 0.0);
+        let hit = hit();
+        let mix = materials.get(mix);
+        assert!(mix.scatter(&ray, &hit, &materials, &textures).is_none());
+        assert_eq!(mix.opacity(&hit, &materials, &textures), 1.0);
+        let emit = materials.get(emissive).emit(&hit, &materials, &textures);
+        assert!(emit.x.is_finite() && emit.x > 0.0);
+        // The guard unwinds, so later lookups nest from the top again
+        assert_eq!(LAYERS.get(), (0, false));
+    }
+}
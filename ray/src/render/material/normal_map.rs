@@ -0,0 +1,152 @@
+use cgmath::{vec3, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{
+        hittable::HitRecord,
+        texture::{TexCoord, Texture},
+        Colour, Ray, Vector3,
+    },
+    vec_repo::{Id, VecRepo},
+};
+
+use super::{layer, Material, MaterialTrait, ScatterRecord};
+
+/// How `NormalMap::texture` is read.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NormalMapKind {
+    /// Tangent space normals stored as colours, blue pointing out of the surface.
+    Normal,
+    /// Heights, white standing `strength` above black.
+    Bump,
+}
+
+/// Bends the shading normal of another material with a texture,
+/// for surface detail too fine to be worth modelling as geometry.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NormalMap {
+    pub base: Id<Material>,
+    pub texture: Id<Texture>,
+    pub kind: NormalMapKind,
+    pub strength: f64,
+}
+
+impl NormalMap {
+    pub fn new(base: Id<Material>, texture: Id<Texture>, kind: NormalMapKind) -> Self {
+        Self {
+            base,
+            texture,
+            kind,
+            strength: 1.0,
+        }
+    }
+
+    /// `hit` with the normal bent by the texture, still facing the ray.
+    fn perturb(&self, ray: &Ray, hit: &HitRecord, textures: &VecRepo<Texture>) -> HitRecord {
+        let frame = hit.tangent_frame();
+        let texture = textures.get(self.texture);
+        let at = TexCoord::from(hit);
+        let outward = match self.kind {
+            NormalMapKind::Normal => {
+                let c = texture
+                    .colour_at(at, textures)
+                    .map(|c| c as f64 * 2.0 - 1.0);
+                frame.to_world(vec3(c.x * self.strength, c.y * self.strength, c.z))
+            }
+            NormalMapKind::Bump => {
+                // One texel, so that neighbouring lookups differ
+                let (width, height) = texture.dimensions().unwrap_or((1024, 1024));
+                let (du, dv) = (1.0 / width as f64, 1.0 / height as f64);
+                let height = |at| texture.value_at(at, textures) * self.strength;
+                let h = height(at);
+                let along_u = TexCoord {
+                    u: at.u + du,
+                    point: at.point + hit.dpdu * du,
+                    ..at
+                };
+                let along_v = TexCoord {
+                    v: at.v + dv,
+                    point: at.point + hit.dpdv * dv,
+                    ..at
+                };
+                let dpdu = hit.dpdu + frame.w * ((height(along_u) - h) / du);
+                let dpdv = hit.dpdv + frame.w * ((height(along_v) - h) / dv);
+                let n = dpdu.cross(dpdv);
+                if n.dot(frame.w) < 0.0 {
+                    -n
+                } else {
+                    n
+                }
+            }
+        };
+        if outward.magnitude2() < 1e-18 {
+            return *hit;
+        }
+        let mut normal = outward.normalize();
+        if !hit.front_face {
+            normal = -normal;
+        }
+        // Keep the viewer above the shading normal, or the base would scatter nothing
+        let wo = -ray.direction;
+        let cos = wo.dot(normal);
+        if cos < 1e-3 {
+            normal = (normal + wo * (1e-3 - cos)).normalize();
+        }
+        HitRecord { normal, ..*hit }
+    }
+}
+
+impl MaterialTrait for NormalMap {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        let hit = self.perturb(ray, hit, textures);
+        layer(materials, self.base).scatter(ray, &hit, materials, textures)
+    }
+
+    fn eval(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let hit = self.perturb(ray, hit, textures);
+        layer(materials, self.base).eval(ray, &hit, direction, materials, textures)
+    }
+
+    fn pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        let hit = self.perturb(ray, hit, textures);
+        layer(materials, self.base).pdf(ray, &hit, direction, materials, textures)
+    }
+
+    fn emit(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        layer(materials, self.base).emit(hit, materials, textures)
+    }
+
+    fn opacity(
+        &self,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        layer(materials, self.base).opacity(hit, materials, textures)
+    }
+}
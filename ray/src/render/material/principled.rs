@@ -0,0 +1,294 @@
+use std::f64::consts::PI;
+
+use cgmath::{ElementWise, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    render::{
+        hittable::HitRecord,
+        microfacet::{fresnel_schlick, multiple_scattering, reflect, Ggx},
+        onb::Onb,
+        random_f64, random_vec_in_sphere,
+        texture::Texture,
+        Colour, Ray, Vector3,
+    },
+    vec_repo::{Id, VecRepo},
+};
+
+use super::{Dielectric, Material, MaterialTrait, ScalarInput, ScatterRecord};
+
+/// Roughness below which the GGX lobes would turn into mirrors that can't be evaluated.
+const MIN_ROUGHNESS: f64 = 0.05;
+
+/// General purpose material after Burley, "Physically Based Shading at Disney", 2012.
+/// Blends a diffuse base with sheen, a GGX specular layer, rough glass and a clearcoat.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Principled {
+    pub base_colour: Id<Texture>,
+    pub metallic: ScalarInput,
+    pub roughness: ScalarInput,
+    /// Reflectance of the dielectric base, `0.5` is an index of refraction of `1.5`.
+    pub specular: ScalarInput,
+    /// Grazing retroreflection, mostly for cloth.
+    pub sheen: ScalarInput,
+    pub clearcoat: ScalarInput,
+    pub clearcoat_roughness: ScalarInput,
+    pub transmission: ScalarInput,
+    pub emission: Id<Texture>,
+    pub emission_strength: f32,
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Self {
+            base_colour: Id::default(),
+            metallic: 0.0.into(),
+            roughness: 0.5.into(),
+            specular: 0.5.into(),
+            sheen: 0.0.into(),
+            clearcoat: 0.0.into(),
+            clearcoat_roughness: 0.1.into(),
+            transmission: 0.0.into(),
+            emission: Id::default(),
+            emission_strength: 0.0,
+        }
+    }
+}
+
+/// Parameters of `Principled` looked up at a hit.
+struct Surface {
+    base: Colour,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    sheen: f64,
+    clearcoat: f64,
+    clearcoat_roughness: f64,
+    transmission: f64,
+}
+
+impl Surface {
+    fn diffuse_weight(&self) -> f64 {
+        (1.0 - self.metallic) * (1.0 - self.transmission)
+    }
+
+    fn dielectric_f0(&self) -> f64 {
+        0.08 * self.specular
+    }
+
+    /// Reflectance of the metallic and opaque dielectric parts together.
+    fn specular_fresnel(&self, cos_i: f64) -> Colour {
+        let white = Colour::new(1.0, 1.0, 1.0);
+        let dielectric = fresnel_schlick(cos_i, white * self.dielectric_f0() as f32);
+        fresnel_schlick(cos_i, self.base) * self.metallic as f32
+            + dielectric * self.diffuse_weight() as f32
+    }
+
+    fn glass(&self) -> Dielectric {
+        let f0 = self.dielectric_f0().sqrt();
+        let mut glass = Dielectric::new((1.0 + f0) / (1.0 - f0));
+        glass.roughness = self.roughness;
+        glass
+    }
+
+    fn clearcoat_fresnel(&self, cos_i: f64) -> f64 {
+        let f0 = Colour::new(0.04, 0.04, 0.04);
+        self.clearcoat * fresnel_schlick(cos_i, f0).x as f64
+    }
+
+    /// Chances of sampling the diffuse, specular, glass and clearcoat lobes.
+    fn lobe_chances(&self, cos_o: f64) -> [f64; 4] {
+        let f0 = self.dielectric_f0() as f32;
+        let dielectric = fresnel_schlick(cos_o, Colour::new(f0, f0, f0)).x as f64;
+        let f = self.specular_fresnel(cos_o);
+        let weights = [
+            self.diffuse_weight() * (1.0 - dielectric),
+            (f.x + f.y + f.z) as f64 / 3.0,
+            (1.0 - self.metallic) * self.transmission,
+            self.clearcoat_fresnel(cos_o),
+        ];
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return [0.0, 1.0, 0.0, 0.0];
+        }
+        weights.map(|w| w / total)
+    }
+}
+
+impl Principled {
+    pub fn new(base_colour: Id<Texture>) -> Self {
+        Self {
+            base_colour,
+            ..Default::default()
+        }
+    }
+
+    fn surface(&self, hit: &HitRecord, textures: &VecRepo<Texture>) -> Surface {
+        let value = |input: &ScalarInput| input.value_at(hit.into(), textures).clamp(0.0, 1.0);
+        Surface {
+            base: textures
+                .get(self.base_colour)
+                .colour_at(hit.into(), textures),
+            metallic: value(&self.metallic),
+            roughness: value(&self.roughness).max(MIN_ROUGHNESS),
+            specular: value(&self.specular),
+            sheen: value(&self.sheen),
+            clearcoat: value(&self.clearcoat),
+            clearcoat_roughness: value(&self.clearcoat_roughness).max(MIN_ROUGHNESS),
+            transmission: value(&self.transmission),
+        }
+    }
+
+    /// BSDF times cosine and sampling density of all lobes together.
+    fn evaluate(
+        &self,
+        surface: &Surface,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> (Colour, f64) {
+        let onb = Onb::from_w(hit.normal);
+        let (wo, wi) = (onb.to_local(-ray.direction), onb.to_local(direction));
+        if wo.z <= 0.0 {
+            return (Colour::new(0.0, 0.0, 0.0), 0.0);
+        }
+        let chances = surface.lobe_chances(wo.z);
+        let glass = surface.glass();
+        let base_weight = (1.0 - surface.clearcoat_fresnel(wo.z)) as f32;
+
+        let mut value = glass
+            .eval(ray, hit, direction, materials, textures)
+            .mul_element_wise(surface.base)
+            * ((1.0 - surface.metallic) * surface.transmission) as f32;
+        let mut pdf = chances[2] * glass.pdf(ray, hit, direction, materials, textures);
+
+        if wi.z > 0.0 {
+            let m = (wo + wi).normalize();
+            let cos_d = wi.dot(m);
+
+            let f0 = surface.dielectric_f0() as f32;
+            let entering = 1.0 - fresnel_schlick(wo.z, Colour::new(f0, f0, f0)).x;
+            let leaving = 1.0 - fresnel_schlick(wi.z, Colour::new(f0, f0, f0)).x;
+            let sheen = (surface.sheen * (1.0 - cos_d).powi(5)) as f32;
+            let diffuse =
+                surface.base * (entering * leaving / PI as f32) + Colour::new(sheen, sheen, sheen);
+            value += diffuse * (surface.diffuse_weight() * wi.z) as f32;
+            pdf += chances[0] * wi.z / PI;
+
+            let ggx = Ggx::new(surface.roughness, 0.0);
+            let f0_mix = surface.specular_fresnel(1.0);
+            let fresnel = surface
+                .specular_fresnel(cos_d)
+                .mul_element_wise(multiple_scattering(surface.roughness, wo.z, f0_mix));
+            value += fresnel * (ggx.d(m) * ggx.g(wo, wi) / (4.0 * wo.z)) as f32;
+            pdf += chances[1] * ggx.visible_normal_pdf(wo, m) / (4.0 * wo.dot(m));
+
+            value *= base_weight;
+
+            let coat = Ggx::new(surface.clearcoat_roughness, 0.0);
+            let coat_value =
+                surface.clearcoat_fresnel(cos_d) * coat.d(m) * coat.g(wo, wi) / (4.0 * wo.z);
+            value += Colour::new(1.0, 1.0, 1.0) * coat_value as f32;
+            pdf += chances[3] * coat.visible_normal_pdf(wo, m) / (4.0 * wo.dot(m));
+        } else {
+            value *= base_weight;
+        }
+        (value, pdf)
+    }
+}
+
+impl MaterialTrait for Principled {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Option<ScatterRecord> {
+        let surface = self.surface(hit, textures);
+        let onb = Onb::from_w(hit.normal);
+        let wo = onb.to_local(-ray.direction);
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let chances = surface.lobe_chances(wo.z);
+
+        let mut pick = random_f64(0.0, 1.0);
+        let lobe = chances
+            .iter()
+            .position(|&chance| {
+                pick -= chance;
+                pick < 0.0
+            })
+            .unwrap_or(chances.len() - 1);
+        let direction = match lobe {
+            0 => (hit.normal + random_vec_in_sphere()).normalize(),
+            1 | 3 => {
+                let roughness = if lobe == 1 {
+                    surface.roughness
+                } else {
+                    surface.clearcoat_roughness
+                };
+                let m = Ggx::new(roughness, 0.0).sample_visible_normal(wo);
+                onb.to_world(reflect(wo, m))
+            }
+            _ => {
+                surface
+                    .glass()
+                    .scatter(ray, hit, materials, textures)?
+                    .ray
+                    .direction
+            }
+        };
+
+        let (value, pdf) = self.evaluate(&surface, ray, hit, direction, materials, textures);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord {
+            ray: Ray::new(hit.point, direction, ray.time),
+            attenuation: value / pdf as f32,
+            pdf: Some(pdf),
+        })
+    }
+
+    fn eval(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        let surface = self.surface(hit, textures);
+        self.evaluate(&surface, ray, hit, direction, materials, textures)
+            .0
+    }
+
+    fn pdf(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        direction: Vector3,
+        materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> f64 {
+        let surface = self.surface(hit, textures);
+        self.evaluate(&surface, ray, hit, direction, materials, textures)
+            .1
+    }
+
+    fn emit(
+        &self,
+        hit: &HitRecord,
+        _materials: &VecRepo<Material>,
+        textures: &VecRepo<Texture>,
+    ) -> Colour {
+        if !hit.front_face {
+            return Colour::new(0.0, 0.0, 0.0);
+        }
+        textures.get(self.emission).colour_at(hit.into(), textures) * self.emission_strength
+    }
+}
@@ -0,0 +1,196 @@
+use std::f64::consts::PI;
+
+use cgmath::{vec3, InnerSpace};
+use lazy_static::lazy_static;
+
+use super::{random_f64, Colour, Vector3};
+
+// All directions are in the local shading frame, where the normal is `z`.
+
+/// Trowbridge-Reitz (GGX) distribution of microfacet normals.
+#[derive(Clone, Copy)]
+pub struct Ggx {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl Ggx {
+    /// Disney mapping of perceptual roughness and anisotropy in `[0, 1]`.
+    pub fn new(roughness: f64, anisotropy: f64) -> Self {
+        let alpha = roughness.clamp(0.0, 1.0).powi(2);
+        let aspect = (1.0 - 0.9 * anisotropy.clamp(0.0, 1.0)).sqrt();
+        Self {
+            alpha_x: alpha / aspect,
+            alpha_y: alpha * aspect,
+        }
+    }
+
+    /// Too smooth to evaluate, should be treated as a perfect mirror.
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    pub fn d(&self, m: Vector3) -> f64 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let e = (m.x / self.alpha_x).powi(2) + (m.y / self.alpha_y).powi(2) + m.z * m.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    fn lambda(&self, w: Vector3) -> f64 {
+        if w.z == 0.0 {
+            return f64::INFINITY;
+        }
+        let tan2 = ((self.alpha_x * w.x).powi(2) + (self.alpha_y * w.y).powi(2)) / (w.z * w.z);
+        ((1.0 + tan2).sqrt() - 1.0) / 2.0
+    }
+
+    pub fn g1(&self, w: Vector3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height-correlated masking-shadowing.
+    pub fn g(&self, wo: Vector3, wi: Vector3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    pub fn sample_visible_normal(&self, wo: Vector3) -> Vector3 {
+        self.visible_normal(wo, random_f64(0.0, 1.0), random_f64(0.0, 1.0))
+    }
+
+    /// Heitz, "Sampling the GGX Distribution of Visible Normals", 2018.
+    fn visible_normal(&self, wo: Vector3, u1: f64, u2: f64) -> Vector3 {
+        let vh = vec3(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).normalize();
+        let len2 = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if len2 > 0.0 {
+            vec3(-vh.y, vh.x, 0.0) / len2.sqrt()
+        } else {
+            vec3(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        vec3(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).normalize()
+    }
+
+    /// Density of `sample_visible_normal` returning `m`.
+    pub fn visible_normal_pdf(&self, wo: Vector3, m: Vector3) -> f64 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(m).max(0.0) * self.d(m) / wo.z
+    }
+}
+
+const TABLE_SIZE: usize = 16;
+
+lazy_static! {
+    /// Directional albedo of a white isotropic GGX surface, indexed by roughness and cosine.
+    static ref ALBEDO_TABLE: [[f64; TABLE_SIZE]; TABLE_SIZE] = {
+        let samples = 32;
+        let mut table = [[0.0; TABLE_SIZE]; TABLE_SIZE];
+        for (i, row) in table.iter_mut().enumerate() {
+            let ggx = Ggx::new((i as f64 / (TABLE_SIZE - 1) as f64).max(0.05), 0.0);
+            for (j, albedo) in row.iter_mut().enumerate() {
+                let cos_o = (j as f64 + 0.5) / TABLE_SIZE as f64;
+                let wo = vec3((1.0 - cos_o * cos_o).sqrt(), 0.0, cos_o);
+                let mut sum = 0.0;
+                for a in 0..samples {
+                    for b in 0..samples {
+                        let u1 = (a as f64 + 0.5) / samples as f64;
+                        let u2 = (b as f64 + 0.5) / samples as f64;
+                        let wi = reflect(wo, ggx.visible_normal(wo, u1, u2));
+                        if wi.z > 0.0 {
+                            sum += ggx.g(wo, wi) / ggx.g1(wo);
+                        }
+                    }
+                }
+                *albedo = sum / (samples * samples) as f64;
+            }
+        }
+        table
+    };
+}
+
+/// Factor restoring energy lost to multiple bounces between microfacets,
+/// after Turquin, "Practical multiple scattering compensation for microfacet models", 2019.
+pub fn multiple_scattering(roughness: f64, cos_o: f64, f0: Colour) -> Colour {
+    let x = roughness.clamp(0.0, 1.0) * (TABLE_SIZE - 1) as f64;
+    let y = (cos_o.clamp(0.0, 1.0) * TABLE_SIZE as f64 - 0.5).clamp(0.0, (TABLE_SIZE - 1) as f64);
+    let (i, j) = (x as usize, y as usize);
+    let (i1, j1) = ((i + 1).min(TABLE_SIZE - 1), (j + 1).min(TABLE_SIZE - 1));
+    let (fx, fy) = (x - i as f64, y - j as f64);
+    let table = &*ALBEDO_TABLE;
+    let albedo = (table[i][j] * (1.0 - fy) + table[i][j1] * fy) * (1.0 - fx)
+        + (table[i1][j] * (1.0 - fy) + table[i1][j1] * fy) * fx;
+    let lost = ((1.0 - albedo) / albedo.max(1e-3)) as f32;
+    Colour::new(1.0, 1.0, 1.0) + f0 * lost
+}
+
+pub fn reflect(wo: Vector3, m: Vector3) -> Vector3 {
+    m * 2.0 * wo.dot(m) - wo
+}
+
+/// Refracts `wo` through a surface with normal `m`, `eta` is the ratio of the indices
+/// on the far side to the near side. `None` on total internal reflection.
+pub fn refract(wo: Vector3, m: Vector3, eta: f64) -> Option<Vector3> {
+    let cos_i = wo.dot(m);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-wo / eta + m * (cos_i / eta - cos_t))
+}
+
+/// Unpolarised Fresnel reflectance of a dielectric, `eta` as in `refract`.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parl = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parl * r_parl + r_perp * r_perp)
+}
+
+/// Unpolarised Fresnel reflectance of a conductor with complex index `eta + i k`.
+pub fn fresnel_conductor(cos_i: f64, eta: Colour, k: Colour) -> Colour {
+    let channel = |eta: f32, k: f32| {
+        let (eta, k) = (eta as f64, k as f64);
+        let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+        let (eta2, k2) = (eta * eta, k * k);
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_i * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        (0.5 * (rp + rs)) as f32
+    };
+    Colour::new(
+        channel(eta.x, k.x),
+        channel(eta.y, k.y),
+        channel(eta.z, k.z),
+    )
+}
+
+pub fn fresnel_schlick(cos_i: f64, f0: Colour) -> Colour {
+    let weight = (1.0 - cos_i.clamp(0.0, 1.0)).powi(5) as f32;
+    f0 + (Colour::new(1.0, 1.0, 1.0) - f0) * weight
+}
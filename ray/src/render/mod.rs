@@ -1,66 +1,100 @@
 use std::io::Write;
 
-use crate::{oidn::OIND, vec_repo::VecRepo};
+use crate::oidn::OIND;
 use cgmath::{ElementWise, InnerSpace, Zero};
-use hittable::{sphere::Sphere, HittableTrait};
-use image::{Rgb, Rgb32FImage};
-use material::{Material, MaterialTrait};
-use texture::Texture;
+use environment::Environment;
+use hittable::{HitRecord, HittableTrait};
+use image::{ImageBuffer, Luma, Rgb, Rgb32FImage};
+use light::{Light, LightTrait};
+use material::MaterialTrait;
 
 use self::scene::{Scene, SceneRef};
 
 pub mod camera;
+pub mod environment;
+pub mod filtering;
 pub mod hittable;
+pub mod light;
 pub mod material;
+pub mod microfacet;
+pub mod noise;
+pub mod onb;
+pub mod procedural;
+pub mod references;
 pub mod scene;
+pub mod scene_file;
+pub mod sky;
+pub mod spectrum;
 pub mod texture;
+pub mod texture_node;
 
 pub type Point3 = cgmath::Point3<f64>;
 pub type Vector3 = cgmath::Vector3<f64>;
 pub type Colour = cgmath::Vector3<f32>;
 
+/// Single channel of floats, for coverage.
+pub type AlphaImage = ImageBuffer<Luma<f32>, Vec<f32>>;
+
 pub struct RenderedImage {
     pub colour: Rgb32FImage,
     pub albedo: Rgb32FImage,
     pub normal: Rgb32FImage,
     pub denoised: Rgb32FImage,
+    /// Share of each pixel covered by the scene, when the background is transparent.
+    /// The colours are then those of the covered part alone.
+    pub alpha: Option<AlphaImage>,
 }
 
 pub fn render<'a>(dims: (u32, u32), scene: &Scene, sample_count: u32, depth: u32) -> RenderedImage {
     use std::time::Instant;
     let now = Instant::now();
 
-    let SceneRef {
-        hittable,
-        camera,
-        background,
-        materials,
-        textures,
-    } = scene.into();
+    let scene: SceneRef = scene.into();
+    let mut lights = scene.lights.clone();
+    let background = scene.textures.get(*scene.background);
+    lights.extend(scene.environment.sun_light(background).map(Light::from));
+    let scene = SceneRef {
+        lights: &lights,
+        ..scene
+    };
 
     let (width, height) = dims;
-    let camera = camera.build_with_dimensions(width, height);
-    let background = textures.get(*background);
+    let camera = scene.camera.build_with_dimensions(width, height);
 
     let mut colour_image = Rgb32FImage::new(width, height);
     let mut albedo_image = Rgb32FImage::new(width, height);
     let mut normal_image = Rgb32FImage::new(width, height);
+    let mut alpha_image = AlphaImage::new(width, height);
 
     for y in 0..height {
         for x in 0..width {
             let u = x as f64 / (width - 1) as f64;
             let v = y as f64 / (height - 1) as f64;
             let r = camera.get_ray(u, v);
-            let (mut colour, albedo, normal) =
-                cast_ray_extended(r, hittable, background, materials, textures, depth);
+            let wavelength = r.wavelength;
+            let (colour, albedo, normal, covered) = cast_ray_extended(r, &scene, depth);
+            let mut colour = spectrum::resolve(colour, wavelength);
+            let mut coverage = covered as u32;
             for _s in 1..sample_count {
                 let r = camera.get_ray(u, v);
-                colour += cast_ray(r, hittable, background, materials, textures, depth)
+                let wavelength = r.wavelength;
+                let (sample, covered) = cast_camera_ray(r, &scene, depth);
+                colour += spectrum::resolve(sample, wavelength);
+                coverage += covered as u32;
             }
-            let pixel = Rgb::<f32>(gamma_correction(colour / sample_count as f32).into());
+            // Missed samples added nothing when the background is transparent,
+            // so the covered ones alone make up the colour
+            let count = if scene.environment.transparent {
+                coverage.max(1)
+            } else {
+                sample_count
+            };
+            let pixel = Rgb::<f32>(gamma_correction(colour / count as f32).into());
             colour_image.put_pixel(x, height - y - 1, pixel);
             albedo_image.put_pixel(x, height - y - 1, Rgb(albedo.into()));
             normal_image.put_pixel(x, height - y - 1, Rgb(normal.into()));
+            let alpha = coverage as f32 / sample_count as f32;
+            alpha_image.put_pixel(x, height - y - 1, Luma([alpha]));
         }
         print!("\r{}/{} done", y + 1, height);
         std::io::stdout().flush().expect("could not flush stdin");
@@ -81,102 +115,312 @@ pub fn render<'a>(dims: (u32, u32), scene: &Scene, sample_count: u32, depth: u32
         albedo: albedo_image,
         normal: normal_image,
         denoised: denoised_image,
+        alpha: scene.environment.transparent.then_some(alpha_image),
     }
 }
 
 pub struct Ray {
     origin: Point3,
     direction: Vector3,
+    time: f64,
+    /// Wavelength in nanometres carried by the path in spectral mode.
+    wavelength: Option<f64>,
+    /// Width of the cone of space seen through a pixel, at the origin
+    /// and gained per unit of distance, for filtering textures.
+    width: f64,
+    spread: f64,
 }
 
 impl Ray {
-    pub fn new(origin: Point3, direction: Vector3) -> Self {
+    pub fn new(origin: Point3, direction: Vector3, time: f64) -> Self {
         Ray {
             origin,
             direction: direction.normalize(),
+            time,
+            wavelength: None,
+            width: 0.0,
+            spread: 0.0,
+        }
+    }
+    pub fn wavelength(&self) -> Option<f64> {
+        self.wavelength
+    }
+    /// Value of `c` at the wavelength of the ray, repeated in all channels.
+    fn project(&self, c: Colour) -> Colour {
+        match self.wavelength {
+            Some(lambda) => {
+                let value = spectrum::rgb_to_spectrum(c, lambda);
+                Colour::new(value, value, value)
+            }
+            None => c,
         }
     }
     pub fn at(&self, t: f64) -> Point3 {
         self.origin + self.direction * t
     }
+    /// The same ray seen from another frame, moved to `origin` and turned to `direction`.
+    fn moved(&self, origin: Point3, direction: Vector3) -> Self {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+            ..*self
+        }
+    }
+    pub fn width_at(&self, t: f64) -> f64 {
+        self.width + self.spread * t
+    }
+    /// Carries over the wavelength and cone of `parent`, which this ray continues from `distance`.
+    fn inherit(&mut self, parent: &Ray, distance: f64) {
+        self.wavelength = parent.wavelength;
+        self.width = parent.width_at(distance);
+        self.spread = parent.spread;
+    }
+}
+
+pub fn cast_ray(ray: Ray, scene: &SceneRef, depth: u32) -> Colour {
+    trace(ray, scene, depth, None)
 }
 
-pub fn cast_ray(
+/// `cast_ray` for rays leaving the camera, which see nothing past the scene when
+/// the background is transparent. Also tells whether the ray hit anything.
+fn cast_camera_ray(ray: Ray, scene: &SceneRef, depth: u32) -> (Colour, bool) {
+    if depth == 0 {
+        return (Colour::zero(), false);
+    }
+    let hit = hit_opaque(&ray, scene, f64::INFINITY);
+    let covered = hit.is_some();
+    if !covered && scene.environment.transparent {
+        return (Colour::zero(), false);
+    }
+    (shade(ray, hit, scene, depth, None), covered)
+}
+
+/// Closest hit along `ray` before `max_dist`, passing through surfaces where
+/// their material is cut away.
+fn hit_opaque(ray: &Ray, scene: &SceneRef, max_dist: f64) -> Option<HitRecord> {
+    let mut min_dist = 0.0001;
+    loop {
+        let hit = scene.hittable.hit_bounded(ray, min_dist, max_dist)?;
+        let material = scene.materials.get(hit.material_id);
+        let opacity = material.opacity(&hit, scene.materials, scene.textures);
+        if opacity >= 1.0 || random_f64(0.0, 1.0) < opacity {
+            return Some(hit);
+        }
+        min_dist = hit.distance + 0.0001;
+    }
+}
+
+/// `prev_pdf` is the density the previous bounce picked `ray` with,
+/// `None` for camera rays and specular bounces, which lights can't be sampled for.
+fn trace(ray: Ray, scene: &SceneRef, depth: u32, prev_pdf: Option<f64>) -> Colour {
+    if depth == 0 {
+        return Colour::zero();
+    }
+    let hit = hit_opaque(&ray, scene, f64::INFINITY);
+    shade(ray, hit, scene, depth, prev_pdf)
+}
+
+/// Light reaching the origin of `ray`, which runs into `hit` first.
+fn shade(
     ray: Ray,
-    hittable: &dyn HittableTrait,
-    background: &Texture,
-    materials: &VecRepo<Material>,
-    textures: &VecRepo<Texture>,
+    hit: Option<HitRecord>,
+    scene: &SceneRef,
     depth: u32,
+    prev_pdf: Option<f64>,
 ) -> Colour {
-    if depth == 0 {
-        return Colour::new(0.0, 0.0, 0.0);
-    }
-    if let Some(hit) = hittable.hit_bounded(&ray, 0.0001, f64::INFINITY) {
-        let emitted = materials
-            .get(hit.material_id)
-            .emit(hit.uv.0, hit.uv.1, textures);
-        match materials.get(hit.material_id).scatter(&ray, &hit, textures) {
-            None => emitted,
-            Some(scattered) => {
-                scattered.attenuation.mul_element_wise(cast_ray(
-                    scattered.ray,
-                    hittable,
-                    background,
-                    materials,
-                    textures,
-                    depth - 1,
-                )) + emitted
-            }
+    let max_dist = hit.as_ref().map_or(f64::INFINITY, |hit| hit.distance);
+    let lights = ray.project(lights_along(&ray, max_dist, prev_pdf, scene));
+
+    let Some(hit) = hit else {
+        return ray.project(background_along(&ray, prev_pdf, scene)) + lights;
+    };
+    let material = scene.materials.get(hit.material_id);
+    let emitted = ray.project(material.emit(&hit, scene.materials, scene.textures));
+    match material.scatter(&ray, &hit, scene.materials, scene.textures) {
+        None => emitted + lights,
+        Some(mut scattered) => {
+            scattered.ray.inherit(&ray, hit.distance);
+            let direct = match scattered.pdf {
+                Some(_) => ray.project(sample_lights(&ray, &hit, scene)),
+                None => Colour::zero(),
+            };
+            ray.project(scattered.attenuation).mul_element_wise(trace(
+                scattered.ray,
+                scene,
+                depth - 1,
+                scattered.pdf,
+            )) + emitted
+                + lights
+                + direct
         }
-    } else {
-        let (u, v) = Sphere::get_uv(&ray.direction);
-        background.colour_at(u, v)
     }
 }
 
+/// `cast_camera_ray` that also gives the albedo and normal seen, to guide the denoiser.
 fn cast_ray_extended(
     ray: Ray,
-    hittable: &dyn HittableTrait,
-    background: &Texture,
-    materials: &VecRepo<Material>,
-    textures: &VecRepo<Texture>,
+    scene: &SceneRef,
     depth: u32,
-) -> (Colour, Colour, cgmath::Vector3<f32>) {
+) -> (Colour, Colour, cgmath::Vector3<f32>, bool) {
     if depth == 0 {
-        return (Colour::zero(), Colour::zero(), cgmath::Vector3::zero());
-    }
-    if let Some(hit) = hittable.hit_bounded(&ray, 0.0001, f64::INFINITY) {
-        let emitted = materials
-            .get(hit.material_id)
-            .emit(hit.uv.0, hit.uv.1, textures);
-        match materials.get(hit.material_id).scatter(&ray, &hit, textures) {
-            None => (emitted, emitted, hit.normal.cast::<f32>().unwrap()),
-            Some(scattered) => {
-                let next_scattered = cast_ray(
-                    scattered.ray,
-                    hittable,
-                    background,
-                    materials,
-                    textures,
-                    depth - 1,
-                );
-                (
-                    scattered.attenuation.mul_element_wise(next_scattered) + emitted,
-                    scattered.attenuation,
-                    hit.normal.cast::<f32>().unwrap(),
-                )
-            }
+        return (
+            Colour::zero(),
+            Colour::zero(),
+            cgmath::Vector3::zero(),
+            false,
+        );
+    }
+    let hit = hit_opaque(&ray, scene, f64::INFINITY);
+    let max_dist = hit.as_ref().map_or(f64::INFINITY, |hit| hit.distance);
+    let lights = ray.project(lights_along(&ray, max_dist, None, scene));
+
+    let Some(hit) = hit else {
+        let normal = (-ray.direction).cast::<f32>().unwrap().normalize();
+        if scene.environment.transparent {
+            return (Colour::zero(), Colour::zero(), normal, false);
         }
-    } else {
-        let (u, v) = Sphere::get_uv(&ray.direction);
-        let c = background.colour_at(u, v);
-        (c, c, (-ray.direction).cast::<f32>().unwrap().normalize())
+        let c = background_colour(&ray, scene);
+        return (ray.project(c) + lights, c, normal, false);
+    };
+    let material = scene.materials.get(hit.material_id);
+    let emitted = material.emit(&hit, scene.materials, scene.textures);
+    match material.scatter(&ray, &hit, scene.materials, scene.textures) {
+        None => (
+            ray.project(emitted) + lights,
+            emitted,
+            hit.normal.cast::<f32>().unwrap(),
+            true,
+        ),
+        Some(mut scattered) => {
+            scattered.ray.inherit(&ray, hit.distance);
+            let direct = match scattered.pdf {
+                Some(_) => ray.project(sample_lights(&ray, &hit, scene)),
+                None => Colour::zero(),
+            };
+            let attenuation = scattered.attenuation;
+            let next_scattered = trace(scattered.ray, scene, depth - 1, scattered.pdf);
+            (
+                ray.project(attenuation).mul_element_wise(next_scattered)
+                    + ray.project(emitted)
+                    + lights
+                    + direct,
+                attenuation,
+                hit.normal.cast::<f32>().unwrap(),
+                true,
+            )
+        }
+    }
+}
+
+fn background_colour(ray: &Ray, scene: &SceneRef) -> Colour {
+    let texture = scene.textures.get(*scene.background);
+    scene
+        .environment
+        .colour(texture, scene.textures, ray.direction)
+}
+
+/// Background seen by `ray`, weighted like `lights_along` when it is sampled as a light.
+fn background_along(ray: &Ray, prev_pdf: Option<f64>, scene: &SceneRef) -> Colour {
+    let colour = background_colour(ray, scene);
+    let texture = scene.textures.get(*scene.background);
+    match prev_pdf {
+        Some(pdf) if Environment::is_sampled(texture) => {
+            let chance = 1.0 / light_count(scene) as f64;
+            let light_pdf = chance * scene.environment.pdf(texture, ray.direction);
+            colour * power_heuristic(pdf, light_pdf) as f32
+        }
+        _ => colour,
+    }
+}
+
+/// Number of lights picked from by `sample_lights`, the background counting as one
+/// when it is bright enough to be sampled.
+fn light_count(scene: &SceneRef) -> usize {
+    let texture = scene.textures.get(*scene.background);
+    scene.lights.len() + Environment::is_sampled(texture) as usize
+}
+
+/// Veach's power heuristic for combining two sampling strategies.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 {
+        return 0.0;
     }
+    a / (a + b)
+}
+
+/// Light from `scene.lights` that `ray` runs into before `max_dist`,
+/// weighted against the chance of having sampled it with `sample_lights` instead.
+fn lights_along(ray: &Ray, max_dist: f64, prev_pdf: Option<f64>, scene: &SceneRef) -> Colour {
+    let chance = 1.0 / light_count(scene) as f64;
+    scene
+        .lights
+        .iter()
+        .filter_map(|light| {
+            let radiance = light.hit(ray, max_dist)?;
+            let weight = match prev_pdf {
+                Some(pdf) => power_heuristic(pdf, chance * light.pdf(ray.origin, ray.direction)),
+                None => 1.0,
+            };
+            Some(radiance * weight as f32)
+        })
+        .fold(Colour::zero(), |sum, c| sum + c)
+}
+
+/// Next event estimation, direct light from one randomly picked light of `scene.lights`
+/// or the background.
+fn sample_lights(ray: &Ray, hit: &HitRecord, scene: &SceneRef) -> Colour {
+    let count = light_count(scene);
+    if count == 0 {
+        return Colour::zero();
+    }
+    let chance = 1.0 / count as f64;
+    let sample = match scene.lights.get(fastrand::usize(..count)) {
+        Some(light) => light.sample(hit.point),
+        None => scene
+            .environment
+            .sample(scene.textures.get(*scene.background), scene.textures),
+    };
+    let Some(sample) = sample else {
+        return Colour::zero();
+    };
+    let shadow_ray = Ray::new(hit.point, sample.direction, ray.time);
+    if hit_opaque(&shadow_ray, scene, sample.distance * (1.0 - 1e-6)).is_some() {
+        return Colour::zero();
+    }
+
+    let material = scene.materials.get(hit.material_id);
+    let value = material.eval(ray, hit, sample.direction, scene.materials, scene.textures);
+    let weight = match sample.pdf {
+        Some(pdf) => {
+            let bsdf_pdf =
+                material.pdf(ray, hit, sample.direction, scene.materials, scene.textures);
+            power_heuristic(chance * pdf, bsdf_pdf) / (chance * pdf)
+        }
+        None => 1.0 / chance,
+    };
+    value.mul_element_wise(sample.radiance) * weight as f32
 }
 
 fn gamma_correction(c: Colour) -> Colour {
-    Colour::new(c.x.sqrt(), c.y.sqrt(), c.z.sqrt())
+    c.map(linear_to_srgb)
+}
+
+/// sRGB transfer curve, from an encoded value to linear light.
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c.max(0.0) * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
 }
 
 fn rgb_to_vec(rgb: &Rgb<u8>) -> Colour {
@@ -0,0 +1,99 @@
+use cgmath::{EuclideanSpace, InnerSpace};
+
+use super::{Point3, Vector3};
+
+/// Scrambles a lattice cell into 32 bits, so the noise needs no stored tables.
+fn hash(x: i64, y: i64, z: i64, seed: u32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f)
+        ^ seed.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^ (h >> 15)
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// Dot product with one of the twelve edge directions of a cube, as in Perlin's improved noise.
+fn gradient(hash: u32, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Smooth gradient noise, roughly in [-1, 1].
+pub fn perlin(p: Point3) -> f64 {
+    let cell = p.map(f64::floor);
+    let (x, y, z) = (p.x - cell.x, p.y - cell.y, p.z - cell.z);
+    let (i, j, k) = (cell.x as i64, cell.y as i64, cell.z as i64);
+    let corner = |di: i64, dj: i64, dk: i64| {
+        let h = hash(i + di, j + dj, k + dk, 0);
+        gradient(h, x - di as f64, y - dj as f64, z - dk as f64)
+    };
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+            lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+        ),
+        lerp(
+            v,
+            lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+            lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+        ),
+    )
+}
+
+/// Distance to the nearest of points scattered one per unit cell, roughly in [0, 1].
+pub fn worley(p: Point3) -> f64 {
+    let cell = p.map(f64::floor);
+    let (i, j, k) = (cell.x as i64, cell.y as i64, cell.z as i64);
+    let mut nearest = f64::INFINITY;
+    for di in -1..=1 {
+        for dj in -1..=1 {
+            for dk in -1..=1 {
+                let (ci, cj, ck) = (i + di, j + dj, k + dk);
+                let jitter = |seed| hash(ci, cj, ck, seed) as f64 / u32::MAX as f64;
+                let feature = Vector3::new(
+                    ci as f64 + jitter(1),
+                    cj as f64 + jitter(2),
+                    ck as f64 + jitter(3),
+                );
+                nearest = nearest.min((feature - p.to_vec()).magnitude2());
+            }
+        }
+    }
+    nearest.sqrt().min(1.0)
+}
+
+/// Fractal sum of `octaves` layers of `noise`, each twice as fine and half as strong,
+/// scaled back to the range of a single layer. With `turbulence` the absolute values
+/// are summed, folding smooth hills into sharp creases.
+pub fn fbm(noise: impl Fn(Point3) -> f64, p: Point3, octaves: u32, turbulence: bool) -> f64 {
+    let (mut sum, mut total) = (0.0, 0.0);
+    let (mut amplitude, mut frequency) = (1.0, 1.0);
+    for _ in 0..octaves.max(1) {
+        let n = noise(p * frequency);
+        sum += amplitude * if turbulence { n.abs() } else { n };
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / total
+}
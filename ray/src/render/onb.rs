@@ -0,0 +1,80 @@
+use cgmath::InnerSpace;
+
+use super::Vector3;
+
+/// Orthonormal basis, `w` is the main axis.
+#[derive(Clone, Copy)]
+pub struct Onb {
+    pub u: Vector3,
+    pub v: Vector3,
+    pub w: Vector3,
+}
+
+impl Onb {
+    pub fn from_w(w: Vector3) -> Self {
+        let w = w.normalize();
+        // Duff et al., "Building an Orthonormal Basis, Revisited"
+        let sign = 1.0f64.copysign(w.z);
+        let a = -1.0 / (sign + w.z);
+        let b = w.x * w.y * a;
+        let u = Vector3::new(1.0 + sign * w.x * w.x * a, sign * b, -sign * w.x);
+        let v = Vector3::new(b, sign + w.y * w.y * a, -w.y);
+        Self { u, v, w }
+    }
+
+    /// `u` follows `along` as far as it can while staying perpendicular to `w`,
+    /// falling back to an arbitrary direction when `along` is parallel to `w`.
+    pub fn from_w_along(w: Vector3, along: Vector3) -> Self {
+        let w = w.normalize();
+        let tangent = along - w * w.dot(along);
+        if tangent.magnitude2() < 1e-18 {
+            return Self::from_w(w);
+        }
+        let u = tangent.normalize();
+        Self {
+            u,
+            v: w.cross(u),
+            w,
+        }
+    }
+
+    pub fn to_local(&self, vec: Vector3) -> Vector3 {
+        Vector3::new(vec.dot(self.u), vec.dot(self.v), vec.dot(self.w))
+    }
+
+    pub fn to_world(&self, vec: Vector3) -> Vector3 {
+        self.u * vec.x + self.v * vec.y + self.w * vec.z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::vec3;
+
+    use super::*;
+
+    fn assert_orthonormal(onb: &Onb) {
+        for (a, b) in [(onb.u, onb.v), (onb.v, onb.w), (onb.w, onb.u)] {
+            assert!(a.dot(b).abs() < 1e-9);
+        }
+        for a in [onb.u, onb.v, onb.w] {
+            assert!((a.magnitude() - 1.0).abs() < 1e-9);
+        }
+        assert!((onb.u.cross(onb.v) - onb.w).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn u_follows_along_on_either_side() {
+        for w in [vec3(0.0, 0.3, 1.0), vec3(0.0, 0.3, -1.0)] {
+            let onb = Onb::from_w_along(w, vec3(2.0, 0.0, 0.0));
+            assert_orthonormal(&onb);
+            assert!((onb.u - vec3(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn along_parallel_to_w_falls_back() {
+        let onb = Onb::from_w_along(vec3(0.0, 0.0, 1.0), vec3(0.0, 0.0, 3.0));
+        assert_orthonormal(&onb);
+    }
+}
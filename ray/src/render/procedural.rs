@@ -0,0 +1,270 @@
+use std::f64::consts::PI;
+
+use cgmath::{point3, MetricSpace};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    noise::{fbm, perlin, worley},
+    texture::TexCoord,
+    Colour, Point3,
+};
+
+/// What a procedural pattern is laid out over.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum TextureSpace {
+    /// Surface coordinates, so the pattern follows the object's uv mapping.
+    #[default]
+    Uv,
+    /// Position in the scene, so the pattern runs through objects like a solid.
+    World,
+}
+
+impl TextureSpace {
+    fn point(self, at: TexCoord, scale: f64) -> Point3 {
+        match self {
+            TextureSpace::Uv => point3(at.u, at.v, 0.0) * scale,
+            TextureSpace::World => at.point * scale,
+        }
+    }
+}
+
+fn lerp(t: f64, a: Colour, b: Colour) -> Colour {
+    a + (b - a) * t.clamp(0.0, 1.0) as f32
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checker {
+    pub space: TextureSpace,
+    /// Squares per unit.
+    pub scale: f64,
+    pub even: Colour,
+    pub odd: Colour,
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self {
+            space: TextureSpace::Uv,
+            scale: 8.0,
+            even: Colour::new(0.9, 0.9, 0.9),
+            odd: Colour::new(0.1, 0.1, 0.1),
+        }
+    }
+}
+
+impl Checker {
+    pub fn colour_at(&self, at: TexCoord) -> Colour {
+        let p = self.space.point(at, self.scale);
+        let sum = match self.space {
+            TextureSpace::Uv => p.x.floor() + p.y.floor(),
+            TextureSpace::World => p.x.floor() + p.y.floor() + p.z.floor(),
+        };
+        if sum.rem_euclid(2.0) < 1.0 {
+            self.even
+        } else {
+            self.odd
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NoiseKind {
+    /// Smooth rolling hills.
+    Perlin,
+    /// Distance to scattered points, giving cells like stone or scales.
+    Worley,
+}
+
+/// Noise shading from `low` to `high`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Noise {
+    pub kind: NoiseKind,
+    pub space: TextureSpace,
+    /// Features per unit.
+    pub scale: f64,
+    /// Layers of finer detail, one for plain noise.
+    pub octaves: u32,
+    pub turbulence: bool,
+    pub low: Colour,
+    pub high: Colour,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            kind: NoiseKind::Perlin,
+            space: TextureSpace::Uv,
+            scale: 8.0,
+            octaves: 4,
+            turbulence: false,
+            low: Colour::new(0.0, 0.0, 0.0),
+            high: Colour::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Noise {
+    pub fn colour_at(&self, at: TexCoord) -> Colour {
+        let p = self.space.point(at, self.scale);
+        let t = match self.kind {
+            NoiseKind::Perlin if self.turbulence => fbm(perlin, p, self.octaves, true),
+            NoiseKind::Perlin => 0.5 + 0.5 * fbm(perlin, p, self.octaves, false),
+            NoiseKind::Worley => fbm(worley, p, self.octaves, self.turbulence),
+        };
+        lerp(t, self.low, self.high)
+    }
+}
+
+/// Veins running across x, bent by turbulence.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Marble {
+    pub space: TextureSpace,
+    /// Veins per unit.
+    pub scale: f64,
+    /// How far turbulence pushes the veins around.
+    pub distortion: f64,
+    pub octaves: u32,
+    pub base: Colour,
+    pub vein: Colour,
+}
+
+impl Default for Marble {
+    fn default() -> Self {
+        Self {
+            space: TextureSpace::World,
+            scale: 2.0,
+            distortion: 5.0,
+            octaves: 6,
+            base: Colour::new(0.9, 0.88, 0.85),
+            vein: Colour::new(0.25, 0.25, 0.3),
+        }
+    }
+}
+
+impl Marble {
+    pub fn colour_at(&self, at: TexCoord) -> Colour {
+        let p = self.space.point(at, self.scale);
+        let turbulence = fbm(perlin, p, self.octaves, true);
+        let wave = (PI * p.x + self.distortion * turbulence).sin();
+        // Thin dark veins between wide light bands
+        lerp((1.0 - wave.abs()).powi(4), self.base, self.vein)
+    }
+}
+
+/// Growth rings around the y axis.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Wood {
+    pub space: TextureSpace,
+    /// Rings per unit.
+    pub scale: f64,
+    /// How far noise wobbles the rings.
+    pub distortion: f64,
+    pub light: Colour,
+    pub dark: Colour,
+}
+
+impl Default for Wood {
+    fn default() -> Self {
+        Self {
+            space: TextureSpace::World,
+            scale: 8.0,
+            distortion: 0.4,
+            light: Colour::new(0.75, 0.55, 0.33),
+            dark: Colour::new(0.45, 0.28, 0.14),
+        }
+    }
+}
+
+impl Wood {
+    pub fn colour_at(&self, at: TexCoord) -> Colour {
+        let p = self.space.point(at, self.scale);
+        let radius = (p.x * p.x + p.z * p.z).sqrt();
+        let rings = radius + self.distortion * fbm(perlin, point3(p.x, p.y * 0.1, p.z), 3, false);
+        lerp(rings.rem_euclid(1.0).powi(3), self.light, self.dark)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RampStop {
+    pub position: f64,
+    pub colour: Colour,
+}
+
+/// Colours blended linearly between stops, held flat past the first and last.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColourRamp {
+    pub stops: Vec<RampStop>,
+}
+
+impl Default for ColourRamp {
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                RampStop {
+                    position: 0.0,
+                    colour: Colour::new(0.0, 0.0, 0.0),
+                },
+                RampStop {
+                    position: 1.0,
+                    colour: Colour::new(1.0, 1.0, 1.0),
+                },
+            ],
+        }
+    }
+}
+
+impl ColourRamp {
+    pub fn colour_at(&self, t: f64) -> Colour {
+        let Some(first) = self.stops.first() else {
+            return Colour::new(0.0, 0.0, 0.0);
+        };
+        let mut below = first;
+        if t <= below.position {
+            return below.colour;
+        }
+        for stop in &self.stops[1..] {
+            if t < stop.position {
+                let width = stop.position - below.position;
+                return lerp((t - below.position) / width, below.colour, stop.colour);
+            }
+            below = stop;
+        }
+        below.colour
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GradientKind {
+    /// Along u.
+    Horizontal,
+    /// Along v.
+    Vertical,
+    /// Out from the middle of the uv square to its edges.
+    Radial,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub ramp: ColourRamp,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self {
+            kind: GradientKind::Horizontal,
+            ramp: ColourRamp::default(),
+        }
+    }
+}
+
+impl Gradient {
+    pub fn colour_at(&self, at: TexCoord) -> Colour {
+        let t = match self.kind {
+            GradientKind::Horizontal => at.u,
+            GradientKind::Vertical => at.v,
+            GradientKind::Radial => point3(at.u, at.v, 0.0).distance(point3(0.5, 0.5, 0.0)) * 2.0,
+        };
+        self.ramp.colour_at(t)
+    }
+}
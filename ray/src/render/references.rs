@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::vec_repo::Id;
+
+use super::{hittable::HasMaterial, material::Material, scene::SceneRef, texture::Texture};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AssetId {
+    Material(Id<Material>),
+    Texture(Id<Texture>),
+}
+
+/// What an id is held by.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Holder {
+    /// The object at this index of `Scene::hittable`.
+    Object(usize),
+    Background,
+    Asset(AssetId),
+}
+
+#[derive(Default)]
+pub struct AssetIds {
+    pub materials: Vec<Id<Material>>,
+    pub textures: Vec<Id<Texture>>,
+}
+
+/// Which parts of a scene use which materials and textures.
+pub struct ReferenceGraph {
+    /// Material of each object, in the order of `Scene::hittable`.
+    pub objects: Vec<Id<Material>>,
+    pub background: Id<Texture>,
+    /// What each material of the scene reads.
+    pub materials: BTreeMap<Id<Material>, AssetIds>,
+    /// Inputs of each texture of the scene.
+    pub textures: BTreeMap<Id<Texture>, Vec<Id<Texture>>>,
+}
+
+impl ReferenceGraph {
+    pub fn new(scene: &SceneRef) -> Self {
+        let objects = scene.hittable.iter().map(|o| o.material_id()).collect();
+        let materials = scene
+            .materials
+            .iter_with_ids()
+            .map(|(id, material)| {
+                let (materials, textures) = material.references();
+                (
+                    id,
+                    AssetIds {
+                        materials,
+                        textures,
+                    },
+                )
+            })
+            .collect();
+        let textures = scene
+            .textures
+            .iter_with_ids()
+            .map(|(id, texture)| (id, texture.inputs()))
+            .collect();
+        Self {
+            objects,
+            background: *scene.background,
+            materials,
+            textures,
+        }
+    }
+
+    /// Every id held, with what holds it.
+    pub fn edges(&self) -> Vec<(Holder, AssetId)> {
+        let mut edges: Vec<_> = self
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(idx, &id)| (Holder::Object(idx), AssetId::Material(id)))
+            .collect();
+        edges.push((Holder::Background, AssetId::Texture(self.background)));
+        for (&id, uses) in &self.materials {
+            let holder = Holder::Asset(AssetId::Material(id));
+            edges.extend(
+                uses.materials
+                    .iter()
+                    .map(|&m| (holder, AssetId::Material(m))),
+            );
+            edges.extend(uses.textures.iter().map(|&t| (holder, AssetId::Texture(t))));
+        }
+        for (&id, inputs) in &self.textures {
+            let holder = Holder::Asset(AssetId::Texture(id));
+            edges.extend(inputs.iter().map(|&t| (holder, AssetId::Texture(t))));
+        }
+        edges
+    }
+
+    /// Ids pointing at nothing, which render as the default material or texture.
+    pub fn dangling(&self) -> Vec<(Holder, AssetId)> {
+        self.edges()
+            .into_iter()
+            .filter(|&(_, id)| !self.contains(id))
+            .collect()
+    }
+
+    /// Materials and textures nothing in the scene leads to. The defaults are always
+    /// used, since whatever is missing falls back to them.
+    pub fn unused(&self) -> AssetIds {
+        let mut reached = BTreeSet::new();
+        let mut pending: Vec<_> = self
+            .objects
+            .iter()
+            .map(|&id| AssetId::Material(id))
+            .collect();
+        pending.push(AssetId::Texture(self.background));
+        while let Some(asset) = pending.pop() {
+            if !reached.insert(asset) {
+                continue;
+            }
+            match asset {
+                AssetId::Material(id) => {
+                    if let Some(uses) = self.materials.get(&id) {
+                        pending.extend(uses.materials.iter().map(|&m| AssetId::Material(m)));
+                        pending.extend(uses.textures.iter().map(|&t| AssetId::Texture(t)));
+                    }
+                }
+                AssetId::Texture(id) => {
+                    if let Some(inputs) = self.textures.get(&id) {
+                        pending.extend(inputs.iter().map(|&t| AssetId::Texture(t)));
+                    }
+                }
+            }
+        }
+        let unused = |asset: AssetId| !reached.contains(&asset);
+        AssetIds {
+            materials: self
+                .materials
+                .keys()
+                .copied()
+                .filter(|&id| id != Id::default() && unused(AssetId::Material(id)))
+                .collect(),
+            textures: self
+                .textures
+                .keys()
+                .copied()
+                .filter(|&id| id != Id::default() && unused(AssetId::Texture(id)))
+                .collect(),
+        }
+    }
+
+    fn contains(&self, asset: AssetId) -> bool {
+        match asset {
+            AssetId::Material(id) => self.materials.contains_key(&id),
+            AssetId::Texture(id) => self.textures.contains_key(&id),
+        }
+    }
+}
@@ -1,17 +1,22 @@
 use cgmath::{point3, vec3};
 use serde::{Deserialize, Serialize};
 
-use crate::vec_repo::{Id, VecRepo};
+use crate::vec_repo::{Id, Names, VecRepo};
 
 use super::{
     camera::CameraSettings,
+    environment::Environment,
     hittable::{
+        cuboid::Cuboid,
         modifiers::{RotateY, Translate},
+        quad::Quad,
         rect::Rect,
         sphere::Sphere,
-        HittableVec,
+        HasMaterial, Hittable, HittableVec,
     },
+    light::Light,
     material::{Dielectric, DiffuseLight, Lambertian, Material},
+    references::{AssetIds, ReferenceGraph},
     texture::Texture,
     Colour,
 };
@@ -23,6 +28,13 @@ pub struct Scene {
     pub background: Id<Texture>,
     pub materials: VecRepo<Material>,
     pub textures: VecRepo<Texture>,
+    #[serde(default)]
+    pub lights: Vec<Light>,
+    #[serde(default)]
+    pub environment: Environment,
+    /// Keyed by index into `hittable`.
+    #[serde(default)]
+    pub object_names: Names<usize>,
 }
 
 #[derive(Clone, Serialize)]
@@ -32,6 +44,9 @@ pub struct SceneRef<'a> {
     pub background: &'a Id<Texture>,
     pub materials: &'a VecRepo<Material>,
     pub textures: &'a VecRepo<Texture>,
+    pub lights: &'a Vec<Light>,
+    pub environment: &'a Environment,
+    pub object_names: &'a Names<usize>,
 }
 
 impl<'a> From<&'a Scene> for SceneRef<'a> {
@@ -42,6 +57,9 @@ impl<'a> From<&'a Scene> for SceneRef<'a> {
             background: &value.background,
             materials: &value.materials,
             textures: &value.textures,
+            lights: &value.lights,
+            environment: &value.environment,
+            object_names: &value.object_names,
         }
     }
 }
@@ -57,34 +75,71 @@ impl Default for Scene {
             background: id,
             materials: Default::default(),
             textures,
+            lights: Vec::new(),
+            environment: Environment::default(),
+            object_names: Names::default(),
         }
     }
 }
 
 impl Scene {
+    pub fn object_by_name(&self, name: &str) -> Option<&Hittable> {
+        self.hittable.get(self.object_names.find(name)?)
+    }
+
+    /// Removes the materials and textures nothing uses, then closes the gaps they
+    /// and earlier removals left, updating every id to match.
+    /// Gives the ids of what was removed, as they were before.
+    pub fn compact(&mut self) -> AssetIds {
+        let unused = ReferenceGraph::new(&(&*self).into()).unused();
+        let materials = self.materials.compact(|id| !unused.materials.contains(&id));
+        let textures = self.textures.compact(|id| !unused.textures.contains(&id));
+        // Dangling ids meant the default before, and still do
+        let material = |id: &mut Id<Material>| *id = materials.get(id).copied().unwrap_or_default();
+        let texture = |id: &mut Id<Texture>| *id = textures.get(id).copied().unwrap_or_default();
+
+        for object in &mut self.hittable {
+            let mut id = object.material_id();
+            material(&mut id);
+            object.set_material_id(id);
+        }
+        for m in self.materials.iter_mut() {
+            let (material_ids, texture_ids) = m.references_mut();
+            material_ids.into_iter().for_each(material);
+            texture_ids.into_iter().for_each(texture);
+        }
+        for t in self.textures.iter_mut() {
+            t.inputs_mut().into_iter().for_each(texture);
+        }
+        texture(&mut self.background);
+        unused
+    }
+
     pub fn cornell_box() -> Self {
         // Use this for testing.
         let mut textures = VecRepo::<Texture>::default();
 
-        let red_tex = textures.insert(Colour::new(0.65, 0.05, 0.05));
-        let white_tex = textures.insert(Colour::new(0.73, 0.73, 0.73));
-        let green_tex = textures.insert(Colour::new(0.12, 0.45, 0.15));
-        let light_tex = textures.insert(Colour::new(1.0, 1.0, 1.0));
-        let black_tex = textures.insert(Colour::new(0., 0., 0.));
+        let red_tex = textures.insert_named("red", Colour::new(0.65, 0.05, 0.05));
+        let white_tex = textures.insert_named("white", Colour::new(0.73, 0.73, 0.73));
+        let green_tex = textures.insert_named("green", Colour::new(0.12, 0.45, 0.15));
+        let light_tex = textures.insert_named("light", Colour::new(1.0, 1.0, 1.0));
+        let black_tex = textures.insert_named("black", Colour::new(0., 0., 0.));
 
         let mut materials = VecRepo::<Material>::default();
 
-        let red_mat = materials.insert(Lambertian { albedo: red_tex });
-        let white_mat = materials.insert(Lambertian { albedo: white_tex });
-        let green_mat = materials.insert(Lambertian { albedo: green_tex });
-        let light_mat = materials.insert(DiffuseLight {
-            emit: light_tex,
-            amplify: 15.0,
-        });
+        let red_mat = materials.insert_named("red_wall", Lambertian { albedo: red_tex });
+        let white_mat = materials.insert_named("white_wall", Lambertian { albedo: white_tex });
+        let green_mat = materials.insert_named("green_wall", Lambertian { albedo: green_tex });
+        let light_mat = materials.insert_named(
+            "light",
+            DiffuseLight {
+                emit: light_tex,
+                amplify: 15.0,
+                two_sided: false,
+            },
+        );
 
-        let glass_mat = materials.insert(Dielectric {
-            refractive_index: 1.5,
-        });
+        let glass_mat = materials.insert_named("glass", Dielectric::new(1.5));
 
         let point000 = point3(0.0, 0.0, 0.0);
 
@@ -97,24 +152,27 @@ impl Scene {
 
         let point555 = point3(555.0, 555.0, 555.0);
 
-        let green_wall = Rect::new(point500, point555, green_mat);
-        let red_wall = Rect::new(point000, point055, red_mat);
-        let floor = Rect::new(point000, point505, white_mat);
-        let ceiling = Rect::new(point050, point555, white_mat);
-        let back_wall = Rect::new(point005, point555, white_mat);
-        let light_source = Rect::new(
+        let wall = |from, to, material| Rect::new(from, to, material).expect("walls are flat");
+        let green_wall = wall(point500, point555, green_mat);
+        let red_wall = wall(point000, point055, red_mat);
+        let floor = wall(point000, point505, white_mat);
+        let ceiling = wall(point050, point555, white_mat);
+        let back_wall = wall(point005, point555, white_mat);
+        // Facing down into the box
+        let light_source = Quad::new(
             point3(213.0, 554.0, 227.0),
-            point3(343.0, 554.0, 322.0),
+            vec3(130.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 95.0),
             light_mat,
         );
 
         let ball = Sphere::new(point3(400.0, 100.0, 80.0), 100.0, glass_mat);
 
-        let box1 = Rect::new(point000, point3(165.0, 330.0, 165.0), white_mat);
+        let box1 = Cuboid::new(point000, point3(165.0, 330.0, 165.0), white_mat);
         let box1 = RotateY::new(Box::new(box1.into()), 15.0);
         let box1 = Translate::new(Box::new(box1.into()), vec3(265.0, 0.0, 295.0));
 
-        let box2 = Rect::new(point000, point3(165.0, 165.0, 165.0), white_mat);
+        let box2 = Cuboid::new(point000, point3(165.0, 165.0, 165.0), white_mat);
         let box2 = RotateY::new(Box::new(box2.into()), -18.0);
         let box2 = Translate::new(Box::new(box2.into()), vec3(130.0, 0.0, 65.0));
 
@@ -141,9 +199,77 @@ impl Scene {
             background: black_tex,
             materials,
             textures: textures.into(),
+            lights: Vec::new(),
+            environment: Environment::default(),
+            object_names: Names::default(),
         };
+        let names = [
+            "green_wall",
+            "red_wall",
+            "floor",
+            "ceiling",
+            "back_wall",
+            "light",
+            "ball",
+            "tall_box",
+            "short_box",
+        ];
+        for (index, name) in names.into_iter().enumerate() {
+            scene.object_names.set(index, name);
+        }
         let serialized = rmp_serde::to_vec(&scene).unwrap();
         scene = rmp_serde::from_slice(&serialized).unwrap();
         scene
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::render::material::Cutout;
+
+    use super::*;
+
+    #[test]
+    fn compact_removes_unused_assets_and_remaps_ids() {
+        let mut scene = Scene::default();
+        let old_background = scene.background;
+        let gone = scene.textures.insert(Colour::new(1.0, 0.0, 0.0));
+        let only_unused = scene.textures.insert(Colour::new(0.0, 1.0, 0.0));
+        let albedo = scene.textures.insert(Colour::new(0.0, 0.0, 1.0));
+        scene.background = scene.textures.insert(Colour::new(1.0, 1.0, 1.0));
+        scene.textures.remove(gone);
+
+        let unused = scene.materials.insert(Lambertian {
+            albedo: only_unused,
+        });
+        let base = scene.materials.insert(Lambertian { albedo });
+        let cutout = scene.materials.insert(Cutout::new(base, albedo));
+        let sphere = |material| Sphere::new(point3(0.0, 0.0, 0.0), 1.0, material).into();
+        scene.hittable.push(sphere(cutout));
+        scene.hittable.push(sphere(Id::new(99u32)));
+
+        let removed = scene.compact();
+        assert!(removed.materials == [unused]);
+        assert!(removed.textures == [old_background, only_unused]);
+
+        assert_eq!(scene.materials.ids().count(), 3);
+        assert_eq!(scene.textures.ids().count(), 3);
+        assert!(scene.hittable[0].material_id() == Id::new(2u32));
+        assert!(
+            scene.hittable[1].material_id() == Id::default(),
+            "a dangling id still means the default"
+        );
+        match scene.materials.get(Id::new(2u32)) {
+            Material::Cutout(cutout) => {
+                assert!(cutout.base == Id::new(1u32));
+                assert!(cutout.mask == Id::new(1u32));
+            }
+            _ => panic!("the cutout moved to the wrong slot"),
+        }
+        match scene.materials.get(Id::new(1u32)) {
+            Material::Lambertian(lambertian) => assert!(lambertian.albedo == Id::new(1u32)),
+            _ => panic!("the base moved to the wrong slot"),
+        }
+        assert!(scene.background == Id::new(2u32));
+    }
+}
@@ -0,0 +1,321 @@
+//! Scenes as files.
+//!
+//! | Bytes  | Content                                             |
+//! |--------|-----------------------------------------------------|
+//! | `0..4` | `MAGIC`, the ASCII text `RTSC`                      |
+//! | `4..6` | Format version, a little endian `u16`               |
+//! | `6..`  | The scene as MessagePack, compressed with zlib      |
+//!
+//! Files from before the header was added are raw compressed MessagePack and count as version 0.
+//!
+//! Fields added with a `serde` default don't need a new version, older files just lack them.
+//! Anything else that changes how a scene is laid out, like a removed or reordered `Hittable`
+//! or `Material` variant, raises `VERSION` and adds a migration from the version before.
+
+use anyhow::{anyhow, bail};
+
+use super::scene::Scene;
+
+pub const MAGIC: &[u8; 4] = b"RTSC";
+pub const VERSION: u16 = 1;
+
+/// MessagePack of a scene laid out for one version, turned into that of the next.
+type Migration = fn(Vec<u8>) -> anyhow::Result<Vec<u8>>;
+
+/// `MIGRATIONS[v]` takes a version `v` scene to version `v + 1`.
+const MIGRATIONS: [Migration; VERSION as usize] = [from_headerless];
+
+/// Version 0 `Metal`s only had `fuzz` for a roughness, and a `Rect` whose corners
+/// differed in every coordinate stood for a box.
+/// Writes today's `Scene`, which must become a version 1 mirror once `VERSION` goes up.
+fn from_headerless(data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let scene: v0::Scene = rmp_serde::from_slice(&data)
+        .map_err(|err| anyhow!("can't convert the version 0 scene: {}", err))?;
+    Ok(rmp_serde::to_vec(&Scene::from(scene))?)
+}
+
+/// Scene types as version 0 laid them out, where they differ from today's.
+mod v0 {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        render::{
+            camera::CameraSettings,
+            hittable::{self, cuboid::Cuboid, modifiers, rect, sphere::Sphere},
+            material::{self, Dielectric, DiffuseLight, Isotropic, Lambertian},
+            scene,
+            texture::Texture,
+            Point3, Vector3,
+        },
+        vec_repo::{Id, VecRepo},
+    };
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Scene {
+        pub hittable: Vec<Hittable>,
+        pub camera: CameraSettings,
+        pub background: Id<Texture>,
+        pub materials: VecRepo<Material>,
+        pub textures: VecRepo<Texture>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum Hittable {
+        Sphere(Sphere),
+        Rect(Rect),
+        Translate(Translate),
+        RotateY(RotateY),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Rect {
+        pub min_point: Point3,
+        pub max_point: Point3,
+        pub material_id: Id<material::Material>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Translate {
+        pub offset: Vector3,
+        pub object: Box<Hittable>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct RotateY {
+        pub sin_y: f64,
+        pub cos_y: f64,
+        pub object: Box<Hittable>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum Material {
+        Lambertian(Lambertian),
+        Metal(Metal),
+        Dielectric(Dielectric),
+        DiffuseLight(DiffuseLight),
+        Isotropic(Isotropic),
+    }
+
+    impl Default for Material {
+        fn default() -> Self {
+            Material::Lambertian(Lambertian {
+                albedo: Id::default(),
+            })
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Metal {
+        pub albedo: Id<Texture>,
+        pub fuzz: f64,
+    }
+
+    impl From<Scene> for scene::Scene {
+        fn from(value: Scene) -> Self {
+            Self {
+                hittable: value.hittable.into_iter().map(Into::into).collect(),
+                camera: value.camera,
+                background: value.background,
+                materials: value.materials.map(Into::into),
+                textures: value.textures,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl From<Hittable> for hittable::Hittable {
+        fn from(value: Hittable) -> Self {
+            match value {
+                Hittable::Sphere(sphere) => sphere.into(),
+                Hittable::Rect(Rect {
+                    min_point,
+                    max_point,
+                    material_id,
+                }) => match rect::Rect::new(min_point, max_point, material_id) {
+                    Some(rect) => rect.into(),
+                    None => Cuboid::new(min_point, max_point, material_id).into(),
+                },
+                Hittable::Translate(Translate { offset, object }) => {
+                    modifiers::Translate::new(Box::new((*object).into()), offset).into()
+                }
+                Hittable::RotateY(RotateY {
+                    sin_y,
+                    cos_y,
+                    object,
+                }) => modifiers::RotateY::new(
+                    Box::new((*object).into()),
+                    sin_y.atan2(cos_y).to_degrees(),
+                )
+                .into(),
+            }
+        }
+    }
+
+    impl From<Material> for material::Material {
+        fn from(value: Material) -> Self {
+            match value {
+                Material::Lambertian(m) => m.into(),
+                Material::Metal(Metal { albedo, fuzz }) => material::Metal {
+                    albedo,
+                    roughness: fuzz,
+                    anisotropy: 0.0,
+                    ior: None,
+                }
+                .into(),
+                Material::Dielectric(m) => m.into(),
+                Material::DiffuseLight(m) => m.into(),
+                Material::Isotropic(m) => m.into(),
+            }
+        }
+    }
+}
+
+pub fn to_bytes(scene: &Scene) -> anyhow::Result<Vec<u8>> {
+    let data = rmp_serde::to_vec(scene)?;
+    let mut bytes = Vec::from(*MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend(miniz_oxide::deflate::compress_to_vec_zlib(&data, 10));
+    Ok(bytes)
+}
+
+/// Reads a scene of this or any older version.
+pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Scene> {
+    let (version, compressed) = match bytes.strip_prefix(MAGIC) {
+        Some([a, b, rest @ ..]) => (u16::from_le_bytes([*a, *b]), rest),
+        Some(_) => bail!("the file ends inside its header"),
+        None => (0, bytes),
+    };
+    if version > VERSION {
+        bail!(
+            "the file is format version {}, newer than the {} this build reads",
+            version,
+            VERSION
+        );
+    }
+    let decompressed = if version == 0 {
+        miniz_oxide::inflate::decompress_to_vec(compressed)
+    } else {
+        miniz_oxide::inflate::decompress_to_vec_zlib(compressed)
+    };
+    let mut data = decompressed.map_err(|err| anyhow!("not a scene file ({:?})", err.status))?;
+    for migration in &MIGRATIONS[version as usize..] {
+        data = migration(data)?;
+    }
+    Ok(rmp_serde::from_slice(&data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{point3, vec3};
+
+    use crate::{
+        render::{
+            camera::CameraSettings,
+            hittable::{sphere::Sphere, HasMaterial, Hittable, HittableTrait},
+            material::{Lambertian, Material},
+            texture::Texture,
+            Colour,
+        },
+        vec_repo::{Id, VecRepo},
+    };
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let scene = Scene::cornell_box();
+        let bytes = to_bytes(&scene).unwrap();
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(bytes[4..6], VERSION.to_le_bytes());
+        let loaded = from_bytes(&bytes).unwrap();
+        assert_eq!(
+            rmp_serde::to_vec(&loaded).unwrap(),
+            rmp_serde::to_vec(&scene).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let mut bytes = to_bytes(&Scene::default()).unwrap();
+        bytes[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        let err = from_bytes(&bytes).err().unwrap().to_string();
+        assert!(err.contains("newer"), "{}", err);
+    }
+
+    fn headerless(scene: &impl serde::Serialize) -> Vec<u8> {
+        let data = rmp_serde::to_vec(scene).unwrap();
+        miniz_oxide::deflate::compress_to_vec(&data, 10)
+    }
+
+    #[test]
+    fn converts_headerless_scenes() {
+        let mut textures = VecRepo::<Texture>::default();
+        let background = textures.insert(Colour::new(0.1, 0.2, 0.3));
+        let mut materials = VecRepo::new(v0::Material::default());
+        materials.insert(v0::Material::Metal(v0::Metal {
+            albedo: background,
+            fuzz: 0.3,
+        }));
+        // The first after the default, in either repo
+        let metal = Id::new(1u32);
+        let rect = |min_point, max_point| {
+            v0::Hittable::Rect(v0::Rect {
+                min_point,
+                max_point,
+                material_id: metal,
+            })
+        };
+        let old = v0::Scene {
+            hittable: vec![
+                v0::Hittable::Sphere(Sphere::new(point3(0.0, 0.0, 0.0), 1.0, Id::default())),
+                rect(point3(0.0, 0.0, 0.0), point3(1.0, 0.0, 1.0)),
+                rect(point3(0.0, 0.0, 0.0), point3(1.0, 2.0, 3.0)),
+                v0::Hittable::RotateY(v0::RotateY {
+                    sin_y: 1.0,
+                    cos_y: 0.0,
+                    object: Box::new(v0::Hittable::Translate(v0::Translate {
+                        offset: vec3(5.0, 0.0, 0.0),
+                        object: Box::new(rect(point3(0.0, 0.0, 0.0), point3(1.0, 2.0, 3.0))),
+                    })),
+                }),
+            ],
+            camera: CameraSettings::default(),
+            background,
+            materials,
+            textures,
+        };
+
+        let scene = from_bytes(&headerless(&old)).unwrap();
+        assert!(scene.background == background);
+        assert!(matches!(scene.hittable[0], Hittable::Sphere(_)));
+        assert!(matches!(scene.hittable[1], Hittable::Rect(_)));
+        let Hittable::Cuboid(cuboid) = &scene.hittable[2] else {
+            panic!("a box Rect must become a Cuboid");
+        };
+        assert!(cuboid.material_id() == metal);
+        // Turned a quarter about y, the box moved out to x = 5 ends up towards -z
+        let bbox = scene.hittable[3].bounding_box().unwrap();
+        assert!((bbox.min.z + 6.0).abs() < 1e-9 && (bbox.max.z + 5.0).abs() < 1e-9);
+        assert!((bbox.max.y - 2.0).abs() < 1e-9);
+
+        let Material::Metal(converted) = scene.materials.get(metal) else {
+            panic!("the Metal must stay a Metal");
+        };
+        assert_eq!(converted.roughness, 0.3);
+        assert_eq!(converted.anisotropy, 0.0);
+        assert!(converted.ior.is_none());
+        assert!(matches!(
+            scene.materials.get_default(),
+            Material::Lambertian(Lambertian { .. })
+        ));
+    }
+
+    #[test]
+    fn unconvertible_headerless_scenes_fail() {
+        let err = from_bytes(&headerless(&("not", "a", "scene")))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("version 0"), "{}", err);
+    }
+}
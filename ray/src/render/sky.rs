@@ -0,0 +1,186 @@
+use std::f64::consts::PI;
+
+use cgmath::{vec3, ElementWise, InnerSpace};
+use serde::{Deserialize, Serialize};
+
+use super::{light::SunLight, spectrum::xyz_to_linear_srgb, Colour, Vector3};
+
+/// Sky luminance of the model, in kcd/m², is scaled by this to come out close to one.
+const LUMINANCE_SCALE: f64 = 1.0 / 15.0;
+/// Illuminance from the sun above the atmosphere, in klx.
+const SUN_ILLUMINANCE: f64 = 128.0;
+const SUN_ANGULAR_DIAMETER: f64 = 0.53;
+
+/// Clear daylight sky after Preetham, Shirley and Smits,
+/// "A Practical Analytic Model for Daylight", 1999.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sky {
+    /// Height of the sun above the horizon, in degrees.
+    pub elevation: f64,
+    /// Direction of the sun around the vertical axis, in degrees.
+    pub azimuth: f64,
+    /// Haziness of the air, from 2 for a very clear day to 10 for a hazy one.
+    pub turbidity: f64,
+    /// Colour of the ground below the horizon.
+    pub ground_albedo: Colour,
+    /// Also light the scene with a sun matching the sky.
+    pub sun: bool,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            elevation: 45.0,
+            azimuth: 0.0,
+            turbidity: 3.0,
+            ground_albedo: Colour::new(0.3, 0.3, 0.3),
+            sun: true,
+        }
+    }
+}
+
+/// Perez et al. luminance distribution, relative to the zenith.
+fn perez([a, b, c, d, e]: [f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+impl Sky {
+    /// Towards the sun.
+    pub fn sun_direction(&self) -> Vector3 {
+        let elevation = self.elevation.to_radians();
+        let azimuth = self.azimuth.to_radians();
+        vec3(
+            elevation.cos() * azimuth.cos(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.sin(),
+        )
+    }
+
+    /// Radiance seen in `direction`, leaving out the sun itself which is left to `sun_light`.
+    pub fn radiance(&self, direction: Vector3) -> Colour {
+        let direction = direction.normalize();
+        if direction.y < 0.0 {
+            return self.ground();
+        }
+        self.sky(direction)
+    }
+
+    /// Sun matching the sky, dimmed and reddened by the air it crosses.
+    pub fn sun_light(&self) -> Option<SunLight> {
+        (self.sun && self.elevation > 0.0).then(|| SunLight {
+            direction: self.sun_direction(),
+            colour: self.sun_transmittance(),
+            strength: (SUN_ILLUMINANCE * LUMINANCE_SCALE) as f32,
+            angular_diameter: SUN_ANGULAR_DIAMETER,
+        })
+    }
+
+    fn turbidity(&self) -> f64 {
+        self.turbidity.clamp(2.0, 10.0)
+    }
+
+    /// Angle between the sun and the zenith, kept above the horizon where the model holds.
+    fn sun_theta(&self) -> f64 {
+        (90.0 - self.elevation.clamp(0.0, 90.0)).to_radians()
+    }
+
+    fn sky(&self, direction: Vector3) -> Colour {
+        let t = self.turbidity();
+        let theta_s = self.sun_theta();
+        let cos_theta = direction.y.max(1e-3);
+        let gamma = direction.dot(self.sun_direction()).clamp(-1.0, 1.0).acos();
+
+        let luminance = [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ];
+        let x = [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ];
+        let y = [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ];
+        let relative = |coefficients| {
+            perez(coefficients, cos_theta, gamma) / perez(coefficients, 1.0, theta_s)
+        };
+
+        let (zenith_luminance, zenith_x, zenith_y) = self.zenith();
+        let luminance = zenith_luminance * relative(luminance);
+        let x = zenith_x * relative(x);
+        let y = zenith_y * relative(y);
+
+        let xyz = [x * luminance / y, luminance, (1.0 - x - y) * luminance / y];
+        let rgb = xyz_to_linear_srgb(xyz).map(|c| (c * LUMINANCE_SCALE).max(0.0) as f32);
+        Colour::from(rgb)
+    }
+
+    /// Luminance in kcd/m² and chromaticity of the sky straight up.
+    fn zenith(&self) -> (f64, f64, f64) {
+        let t = self.turbidity();
+        let theta_s = self.sun_theta();
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let angles = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+        let chromaticity = |m: [[f64; 4]; 3]| {
+            let row = |r: [f64; 4]| r.iter().zip(angles).map(|(a, b)| a * b).sum::<f64>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        (luminance, x, y)
+    }
+
+    /// Share of sunlight reaching the ground in red, green and blue,
+    /// from Rayleigh and aerosol scattering along the path through the air.
+    fn sun_transmittance(&self) -> Colour {
+        let theta = 90.0 - self.elevation.clamp(0.0, 90.0);
+        let air_mass = 1.0 / (theta.to_radians().cos() + 0.15 * (93.885 - theta).powf(-1.253));
+        let beta = 0.04608 * self.turbidity() - 0.04586;
+        // Wavelengths in micrometres
+        Colour::from([0.65, 0.55, 0.45].map(|lambda: f64| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * air_mass).exp();
+            (rayleigh * aerosol) as f32
+        }))
+    }
+
+    /// Diffuse ground lit by the sky, and by the sun when it is on.
+    fn ground(&self) -> Colour {
+        // Cosine weighted directions, so their average times pi is the irradiance
+        let mut irradiance = Colour::new(0.0, 0.0, 0.0);
+        for ring in 0..4 {
+            let cos = (1.0 - (ring as f64 + 0.5) / 4.0).sqrt();
+            let sin = (1.0 - cos * cos).sqrt();
+            for step in 0..4 {
+                let phi = self.azimuth.to_radians() + (step as f64 + 0.5) * PI / 2.0;
+                let direction = vec3(sin * phi.cos(), cos, -sin * phi.sin());
+                irradiance += self.sky(direction) * (PI / 16.0) as f32;
+            }
+        }
+        if let Some(sun) = self.sun_light() {
+            let cos = self.elevation.to_radians().sin() as f32;
+            irradiance += sun.colour * sun.strength * cos;
+        }
+        self.ground_albedo.mul_element_wise(irradiance) / PI as f32
+    }
+}
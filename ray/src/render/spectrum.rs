@@ -0,0 +1,128 @@
+use lazy_static::lazy_static;
+
+use super::{random_f64, Colour};
+
+/// Range of wavelengths in nanometres sampled in spectral mode.
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 720.0;
+
+pub fn sample_wavelength() -> f64 {
+    random_f64(LAMBDA_MIN, LAMBDA_MAX)
+}
+
+// Smits, "An RGB-to-Spectrum Conversion for Reflectances", 1999.
+// Ten evenly spaced bins covering the sampled range.
+const WHITE: [f32; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const CYAN: [f32; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const MAGENTA: [f32; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const YELLOW: [f32; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const RED: [f32; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const GREEN: [f32; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const BLUE: [f32; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+fn basis_at(basis: &[f32; 10], lambda: f64) -> f32 {
+    let bin_width = (LAMBDA_MAX - LAMBDA_MIN) / basis.len() as f64;
+    let x = ((lambda - LAMBDA_MIN) / bin_width - 0.5).clamp(0.0, (basis.len() - 1) as f64);
+    let i = (x as usize).min(basis.len() - 2);
+    let t = (x - i as f64) as f32;
+    basis[i] * (1.0 - t) + basis[i + 1] * t
+}
+
+/// Value at `lambda` of a smooth spectrum with the colour `c`.
+pub fn rgb_to_spectrum(c: Colour, lambda: f64) -> f32 {
+    let at = |basis| basis_at(basis, lambda);
+    let (r, g, b) = (c.x, c.y, c.z);
+    if r <= g && r <= b {
+        let base = r * at(&WHITE);
+        if g <= b {
+            base + (g - r) * at(&CYAN) + (b - g) * at(&BLUE)
+        } else {
+            base + (b - r) * at(&CYAN) + (g - b) * at(&GREEN)
+        }
+    } else if g <= r && g <= b {
+        let base = g * at(&WHITE);
+        if r <= b {
+            base + (r - g) * at(&MAGENTA) + (b - r) * at(&BLUE)
+        } else {
+            base + (b - g) * at(&MAGENTA) + (r - b) * at(&RED)
+        }
+    } else {
+        let base = b * at(&WHITE);
+        if r <= g {
+            base + (r - b) * at(&YELLOW) + (g - r) * at(&GREEN)
+        } else {
+            base + (g - b) * at(&YELLOW) + (r - g) * at(&RED)
+        }
+    }
+}
+
+/// CIE 1931 colour matching functions, multi-lobe fit from
+/// Wyman et al., "Simple Analytic Approximations to the CIE XYZ Color Matching Functions", 2013.
+pub fn cie_xyz(lambda: f64) -> [f64; 3] {
+    let g = |mu: f64, sigma1: f64, sigma2: f64| {
+        let sigma = if lambda < mu { sigma1 } else { sigma2 };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+    [
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    ]
+}
+
+pub(super) fn xyz_to_linear_srgb([x, y, z]: [f64; 3]) -> [f64; 3] {
+    [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ]
+}
+
+lazy_static! {
+    /// Averages of the matching functions in sRGB over the sampled range,
+    /// so that a constant spectrum turns back into white.
+    static ref RGB_AVERAGE: [f64; 3] = {
+        let steps = 1000;
+        let mut sum = [0.0; 3];
+        for i in 0..steps {
+            let lambda = LAMBDA_MIN + (i as f64 + 0.5) * (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+            let rgb = xyz_to_linear_srgb(cie_xyz(lambda));
+            (0..3).for_each(|c| sum[c] += rgb[c] / steps as f64);
+        }
+        sum
+    };
+}
+
+/// Colour contributed by a radiance `value` carried at a uniformly sampled `lambda`.
+pub fn spectrum_to_rgb(value: f32, lambda: f64) -> Colour {
+    let rgb = xyz_to_linear_srgb(cie_xyz(lambda));
+    let average = &*RGB_AVERAGE;
+    Colour::new(
+        (rgb[0] / average[0]) as f32,
+        (rgb[1] / average[1]) as f32,
+        (rgb[2] / average[2]) as f32,
+    ) * value
+}
+
+/// Colour of a path sample, which only carries a value in the first channel
+/// when it was traced at a single `wavelength`.
+pub fn resolve(c: Colour, wavelength: Option<f64>) -> Colour {
+    match wavelength {
+        Some(lambda) => spectrum_to_rgb(c.x, lambda),
+        None => c,
+    }
+}
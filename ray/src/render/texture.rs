@@ -3,39 +3,175 @@ use std::{
     collections::hash_map::DefaultHasher,
     hash::{Hash, Hasher},
     ops::Deref,
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 
+use cgmath::InnerSpace;
+use image::{GrayImage, Luma, Rgb, Rgb32FImage};
+use lazy_static::lazy_static;
+
 use serde::{
     de::{self, MapAccess, SeqAccess, Visitor},
     ser::SerializeStruct,
     Deserialize, Serialize,
 };
 
-use super::{rgb_to_vec, Colour};
+use super::{
+    environment::{equirect_direction, Distribution},
+    filtering::{Decoded, MipChain, Sampler},
+    hittable::HitRecord,
+    procedural::{Checker, Gradient, Marble, Noise, Wood},
+    sky::Sky,
+    srgb_to_linear,
+    texture_node::{Add, HueSaturation, Invert, Lerp, Multiply, Ramp, UvTransform},
+    Colour, Point3, Vector3,
+};
+use crate::vec_repo::{Id, VecRepo};
 
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum Texture {
     Colour(Colour),
     Image(Image),
+    HdrImage(HdrImage),
+    /// Daylight sky, mapped like an equirectangular image.
+    Sky(Sky),
+    Checker(Checker),
+    Noise(Noise),
+    Marble(Marble),
+    Wood(Wood),
+    Gradient(Gradient),
+    Multiply(Multiply),
+    Add(Add),
+    Lerp(Lerp),
+    Ramp(Ramp),
+    HueSaturation(HueSaturation),
+    Invert(Invert),
+    UvTransform(UvTransform),
+}
+
+/// Where a texture is looked up: surface coordinates for mapped textures,
+/// and the point in space for solid ones.
+#[derive(Clone, Copy)]
+pub struct TexCoord {
+    pub u: f64,
+    pub v: f64,
+    pub point: Point3,
+    /// Width of the area seen through a pixel, along u and v.
+    pub footprint: (f64, f64),
+}
+
+impl TexCoord {
+    /// Just surface coordinates, with the point laid out on the uv square.
+    pub fn uv(u: f64, v: f64) -> Self {
+        Self {
+            u,
+            v,
+            point: Point3::new(u, v, 0.0),
+            footprint: (0.0, 0.0),
+        }
+    }
+}
+
+impl From<&HitRecord> for TexCoord {
+    fn from(hit: &HitRecord) -> Self {
+        let along = |tangent: Vector3| {
+            let length = tangent.magnitude();
+            if length > 0.0 {
+                hit.footprint / length
+            } else {
+                0.0
+            }
+        };
+        Self {
+            u: hit.uv.0,
+            v: hit.uv.1,
+            point: hit.point,
+            footprint: (along(hit.dpdu), along(hit.dpdv)),
+        }
+    }
+}
+
+/// Pixel of a `width` by `height` image holding the point `(u, v)`.
+pub(super) fn pixel_at(u: f64, v: f64, width: u32, height: u32) -> (u32, u32) {
+    let i = (u.clamp(0.0, 1.0) * (width as f64)) as u32;
+    let j = (v.clamp(0.0, 1.0) * (height as f64)) as u32;
+    (i.min(width - 1), j.min(height - 1))
 }
 
 impl Texture {
-    pub fn colour_at(&self, u: f64, v: f64) -> Colour {
+    /// `textures` holds the inputs of textures built from others.
+    pub fn colour_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> Colour {
         match self {
             Self::Colour(c) => c.clone(),
-            Self::Image(img) => {
-                let (width, height) = img.dimensions();
-                let mut i = (u.clamp(0.0, 1.0) * (width as f64)) as u32;
-                let mut j = (v.clamp(0.0, 1.0) * (height as f64)) as u32;
+            Self::Image(img) => img.sampler.colour_at(&img.decoded(), &img.mips, at),
+            Self::HdrImage(img) => img.sampler.colour_at(&img.decoded(), &img.mips, at),
+            Self::Sky(sky) => sky.radiance(equirect_direction(at.u, at.v)),
+            Self::Checker(checker) => checker.colour_at(at),
+            Self::Noise(noise) => noise.colour_at(at),
+            Self::Marble(marble) => marble.colour_at(at),
+            Self::Wood(wood) => wood.colour_at(at),
+            Self::Gradient(gradient) => gradient.colour_at(at),
+            Self::Multiply(node) => node.colour_at(at, textures),
+            Self::Add(node) => node.colour_at(at, textures),
+            Self::Lerp(node) => node.colour_at(at, textures),
+            Self::Ramp(node) => node.colour_at(at, textures),
+            Self::HueSaturation(node) => node.colour_at(at, textures),
+            Self::Invert(node) => node.colour_at(at, textures),
+            Self::UvTransform(node) => node.colour_at(at, textures),
+        }
+    }
 
-                i = if i >= width { i - 1 } else { i };
-                j = if j >= height { j - 1 } else { j };
+    /// Coverage, zero where the texture is see-through.
+    /// Only images with an alpha channel have any transparency.
+    pub fn alpha_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> f64 {
+        match self {
+            Self::Image(img) => img.alpha_at(at),
+            Self::UvTransform(node) => node.alpha_at(at, textures),
+            _ => 1.0,
+        }
+    }
 
-                rgb_to_vec(img.get_pixel(i, j))
-            }
+    /// Textures this one is built from.
+    pub fn inputs(&self) -> Vec<Id<Texture>> {
+        match self {
+            Self::Multiply(node) => vec![node.a, node.b],
+            Self::Add(node) => vec![node.a, node.b],
+            Self::Lerp(node) => vec![node.a, node.b, node.mask],
+            Self::Ramp(node) => vec![node.input],
+            Self::HueSaturation(node) => vec![node.input],
+            Self::Invert(node) => vec![node.input],
+            Self::UvTransform(node) => vec![node.input],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn inputs_mut(&mut self) -> Vec<&mut Id<Texture>> {
+        match self {
+            Self::Multiply(node) => vec![&mut node.a, &mut node.b],
+            Self::Add(node) => vec![&mut node.a, &mut node.b],
+            Self::Lerp(node) => vec![&mut node.a, &mut node.b, &mut node.mask],
+            Self::Ramp(node) => vec![&mut node.input],
+            Self::HueSaturation(node) => vec![&mut node.input],
+            Self::Invert(node) => vec![&mut node.input],
+            Self::UvTransform(node) => vec![&mut node.input],
+            _ => Vec::new(),
         }
     }
+
+    /// Size in pixels of image textures.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match self {
+            Self::Image(img) => Some(img.dimensions()),
+            Self::HdrImage(img) => Some(img.dimensions()),
+            _ => None,
+        }
+    }
+
+    /// Relative luminance, for textures driving a single value.
+    pub fn value_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> f64 {
+        let c = self.colour_at(at, textures);
+        (0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z) as f64
+    }
 }
 
 impl From<Colour> for Texture {
@@ -50,25 +186,233 @@ impl From<Image> for Texture {
     }
 }
 
+impl From<HdrImage> for Texture {
+    fn from(value: HdrImage) -> Self {
+        Texture::HdrImage(value)
+    }
+}
+
+impl From<Sky> for Texture {
+    fn from(value: Sky) -> Self {
+        Texture::Sky(value)
+    }
+}
+
+impl From<Checker> for Texture {
+    fn from(value: Checker) -> Self {
+        Texture::Checker(value)
+    }
+}
+
+impl From<Noise> for Texture {
+    fn from(value: Noise) -> Self {
+        Texture::Noise(value)
+    }
+}
+
+impl From<Marble> for Texture {
+    fn from(value: Marble) -> Self {
+        Texture::Marble(value)
+    }
+}
+
+impl From<Wood> for Texture {
+    fn from(value: Wood) -> Self {
+        Texture::Wood(value)
+    }
+}
+
+impl From<Gradient> for Texture {
+    fn from(value: Gradient) -> Self {
+        Texture::Gradient(value)
+    }
+}
+
+impl From<Multiply> for Texture {
+    fn from(value: Multiply) -> Self {
+        Texture::Multiply(value)
+    }
+}
+
+impl From<Add> for Texture {
+    fn from(value: Add) -> Self {
+        Texture::Add(value)
+    }
+}
+
+impl From<Lerp> for Texture {
+    fn from(value: Lerp) -> Self {
+        Texture::Lerp(value)
+    }
+}
+
+impl From<Ramp> for Texture {
+    fn from(value: Ramp) -> Self {
+        Texture::Ramp(value)
+    }
+}
+
+impl From<HueSaturation> for Texture {
+    fn from(value: HueSaturation) -> Self {
+        Texture::HueSaturation(value)
+    }
+}
+
+impl From<Invert> for Texture {
+    fn from(value: Invert) -> Self {
+        Texture::Invert(value)
+    }
+}
+
+impl From<UvTransform> for Texture {
+    fn from(value: UvTransform) -> Self {
+        Texture::UvTransform(value)
+    }
+}
+
 impl Default for Texture {
     fn default() -> Self {
         Texture::Colour([0.5, 0.5, 0.5].into())
     }
 }
 
-#[derive(Clone, Eq)]
+/// How the values stored in an image turn into colours.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ColourSpace {
+    /// Gamma encoded, as photos and painted textures are usually saved.
+    #[default]
+    Srgb,
+    /// Light stored as it is, like in HDR and EXR files.
+    Linear,
+    /// Not colour at all, such as normals or roughness, so read unchanged.
+    Raw,
+}
+
+lazy_static! {
+    /// Every 8-bit sRGB value decoded, since the curve is slow to evaluate.
+    static ref SRGB_TABLE: [f32; 256] = std::array::from_fn(|n| srgb_to_linear(n as f32 / 255.0));
+}
+
+impl ColourSpace {
+    pub fn decode(self, c: Colour) -> Colour {
+        match self {
+            ColourSpace::Srgb => c.map(srgb_to_linear),
+            ColourSpace::Linear | ColourSpace::Raw => c,
+        }
+    }
+
+    pub fn decode_u8(self, rgb: &Rgb<u8>) -> Colour {
+        match self {
+            ColourSpace::Srgb => Colour::from(rgb.0.map(|n| SRGB_TABLE[n as usize])),
+            ColourSpace::Linear | ColourSpace::Raw => Colour::from(rgb.0.map(|n| n as f32 / 255.0)),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Image {
     image: Arc<image::RgbImage>,
     hash: u64,
+    pub sampler: Sampler,
+    colour_space: ColourSpace,
+    /// Kept apart from the colours, and only when some pixel isn't opaque.
+    alpha: Option<Arc<GrayImage>>,
+    /// Built on first use by trilinear filtering.
+    mips: Arc<OnceLock<MipChain>>,
+    alpha_mips: Arc<OnceLock<MipChain>>,
 }
 
 impl Image {
     pub fn new(image: image::RgbImage) -> Self {
+        Self::with_alpha(image, None)
+    }
+
+    /// Keeps the alpha channel of `image`, for cutouts.
+    pub fn from_rgba(image: image::RgbaImage) -> Self {
+        let opaque = image.pixels().all(|pixel| pixel.0[3] == u8::MAX);
+        let alpha = (!opaque).then(|| {
+            GrayImage::from_fn(image.width(), image.height(), |i, j| {
+                Luma([image.get_pixel(i, j).0[3]])
+            })
+        });
+        let rgb = image::RgbImage::from_fn(image.width(), image.height(), |i, j| {
+            let [r, g, b, _] = image.get_pixel(i, j).0;
+            Rgb([r, g, b])
+        });
+        Self::with_alpha(rgb, alpha)
+    }
+
+    fn with_alpha(image: image::RgbImage, alpha: Option<GrayImage>) -> Self {
         let mut hasher = DefaultHasher::new();
         image.hash(&mut hasher);
+        if let Some(ref alpha) = alpha {
+            alpha.hash(&mut hasher);
+        }
         Self {
             image: Arc::new(image),
             hash: hasher.finish(),
+            sampler: Sampler::default(),
+            colour_space: ColourSpace::default(),
+            alpha: alpha.map(Arc::new),
+            mips: Default::default(),
+            alpha_mips: Default::default(),
+        }
+    }
+
+    /// Fails when the size doesn't fit the data, as in a damaged file.
+    fn from_parts(
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+        alpha: Option<Vec<u8>>,
+        hash: u64,
+        sampler: Sampler,
+        colour_space: ColourSpace,
+    ) -> Result<Self, &'static str> {
+        let image =
+            image::RgbImage::from_raw(width, height, data).ok_or("Size and data should match")?;
+        let alpha = match alpha {
+            Some(alpha) => Some(Arc::new(
+                GrayImage::from_raw(width, height, alpha).ok_or("Size and alpha should match")?,
+            )),
+            None => None,
+        };
+        Ok(Self {
+            image: Arc::new(image),
+            hash,
+            sampler,
+            colour_space,
+            alpha,
+            mips: Default::default(),
+            alpha_mips: Default::default(),
+        })
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.alpha.is_some()
+    }
+
+    fn alpha_at(&self, at: TexCoord) -> f64 {
+        self.alpha.as_ref().map_or(1.0, |alpha| {
+            self.sampler.colour_at(&**alpha, &self.alpha_mips, at).x as f64
+        })
+    }
+
+    pub fn colour_space(&self) -> ColourSpace {
+        self.colour_space
+    }
+
+    pub fn set_colour_space(&mut self, colour_space: ColourSpace) {
+        if self.colour_space != colour_space {
+            self.colour_space = colour_space;
+            self.mips = Default::default();
+        }
+    }
+
+    fn decoded(&self) -> Decoded<'_, image::RgbImage> {
+        Decoded {
+            image: &self.image,
+            space: self.colour_space,
         }
     }
 }
@@ -82,6 +426,8 @@ impl PartialEq for Image {
     }
 }
 
+impl Eq for Image {}
+
 impl Hash for Image {
     fn hash<H: Hasher>(&self, state: &mut H) {
         state.write_u64(self.hash);
@@ -101,11 +447,14 @@ impl Serialize for Image {
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("Image", 4)?;
+        let mut state = serializer.serialize_struct("Image", 7)?;
         state.serialize_field("width", &self.width())?;
         state.serialize_field("height", &self.height())?;
         state.serialize_field("data", self.as_raw())?;
         state.serialize_field("hash", &self.hash)?;
+        state.serialize_field("sampler", &self.sampler)?;
+        state.serialize_field("colour_space", &self.colour_space)?;
+        state.serialize_field("alpha", &self.alpha.as_ref().map(|alpha| alpha.as_raw()))?;
         state.end()
     }
 }
@@ -120,8 +469,19 @@ impl<'de> Deserialize<'de> for Image {
             Height,
             Data,
             Hash,
+            Sampler,
+            ColourSpace,
+            Alpha,
         }
-        const FIELDS: &'static [&'static str] = &["width", "height", "data", "hash"];
+        const FIELDS: &[&str] = &[
+            "width",
+            "height",
+            "data",
+            "hash",
+            "sampler",
+            "colour_space",
+            "alpha",
+        ];
         impl<'de> Deserialize<'de> for Field {
             fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
             where
@@ -132,7 +492,9 @@ impl<'de> Deserialize<'de> for Image {
                     type Value = Field;
 
                     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter.write_str("`width` or `height` or `data` or `hash`")
+                        formatter.write_str(
+                            "`width`, `height`, `data`, `hash`, `sampler`, `colour_space` or `alpha`",
+                        )
                     }
 
                     fn visit_str<E>(self, value: &str) -> Result<Field, E>
@@ -144,6 +506,9 @@ impl<'de> Deserialize<'de> for Image {
                             "height" => Ok(Field::Height),
                             "data" => Ok(Field::Data),
                             "hash" => Ok(Field::Hash),
+                            "sampler" => Ok(Field::Sampler),
+                            "colour_space" => Ok(Field::ColourSpace),
+                            "alpha" => Ok(Field::Alpha),
                             _ => Err(de::Error::unknown_field(value, FIELDS)),
                         }
                     }
@@ -177,14 +542,13 @@ impl<'de> Deserialize<'de> for Image {
                 let hash = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+                // Missing from files saved before images had samplers
+                let sampler = seq.next_element()?.unwrap_or_default();
+                let colour_space = seq.next_element()?.unwrap_or_default();
+                let alpha = seq.next_element()?.flatten();
 
-                Ok(Image {
-                    image: Arc::new(
-                        image::RgbImage::from_raw(width, height, data)
-                            .expect("Size and data should match"),
-                    ),
-                    hash,
-                })
+                Image::from_parts(width, height, data, alpha, hash, sampler, colour_space)
+                    .map_err(de::Error::custom)
             }
 
             fn visit_map<V>(self, mut map: V) -> Result<Image, V::Error>
@@ -195,6 +559,9 @@ impl<'de> Deserialize<'de> for Image {
                 let mut height = None;
                 let mut data = None;
                 let mut hash = None;
+                let mut sampler = None;
+                let mut colour_space = None;
+                let mut alpha = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::Width => {
@@ -221,22 +588,187 @@ impl<'de> Deserialize<'de> for Image {
                             }
                             hash = Some(map.next_value()?);
                         }
+                        Field::Sampler => {
+                            if sampler.is_some() {
+                                return Err(de::Error::duplicate_field("sampler"));
+                            }
+                            sampler = Some(map.next_value()?);
+                        }
+                        Field::ColourSpace => {
+                            if colour_space.is_some() {
+                                return Err(de::Error::duplicate_field("colour_space"));
+                            }
+                            colour_space = Some(map.next_value()?);
+                        }
+                        Field::Alpha => {
+                            if alpha.is_some() {
+                                return Err(de::Error::duplicate_field("alpha"));
+                            }
+                            alpha = Some(map.next_value()?);
+                        }
                     }
                 }
                 let width = width.ok_or_else(|| de::Error::missing_field("width"))?;
                 let height = height.ok_or_else(|| de::Error::missing_field("height"))?;
                 let data = data.ok_or_else(|| de::Error::missing_field("data"))?;
                 let hash = hash.ok_or_else(|| de::Error::missing_field("hash"))?;
-                Ok(Image {
-                    image: Arc::new(
-                        image::RgbImage::from_raw(width, height, data)
-                            .expect("Size and data should match"),
-                    ),
+                Image::from_parts(
+                    width,
+                    height,
+                    data,
+                    alpha.flatten(),
                     hash,
-                })
+                    sampler.unwrap_or_default(),
+                    colour_space.unwrap_or_default(),
+                )
+                .map_err(de::Error::custom)
             }
         }
 
         deserializer.deserialize_struct("Image", FIELDS, ImageVisitor)
     }
 }
+
+/// Floating point image, for lighting from HDR environment maps.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "HdrImageData", into = "HdrImageData")]
+pub struct HdrImage {
+    image: Arc<Rgb32FImage>,
+    hash: u64,
+    pub sampler: Sampler,
+    colour_space: ColourSpace,
+    mips: Arc<OnceLock<MipChain>>,
+    /// Built on first use when importance sampling the image as a background.
+    pub(super) distribution: Arc<OnceLock<Distribution>>,
+}
+
+impl HdrImage {
+    pub fn new(image: Rgb32FImage) -> Self {
+        let mut hasher = DefaultHasher::new();
+        image.dimensions().hash(&mut hasher);
+        image
+            .as_raw()
+            .iter()
+            .for_each(|value| value.to_bits().hash(&mut hasher));
+        Self {
+            image: Arc::new(image),
+            hash: hasher.finish(),
+            sampler: Sampler::default(),
+            colour_space: ColourSpace::Linear,
+            mips: Default::default(),
+            distribution: Default::default(),
+        }
+    }
+
+    /// Linear unless the pixels were widened from 16-bit sRGB.
+    pub fn colour_space(&self) -> ColourSpace {
+        self.colour_space
+    }
+
+    pub fn set_colour_space(&mut self, colour_space: ColourSpace) {
+        if self.colour_space != colour_space {
+            self.colour_space = colour_space;
+            self.mips = Default::default();
+            self.distribution = Default::default();
+        }
+    }
+
+    fn decoded(&self) -> Decoded<'_, Rgb32FImage> {
+        Decoded {
+            image: &self.image,
+            space: self.colour_space,
+        }
+    }
+}
+
+impl PartialEq for HdrImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl Eq for HdrImage {}
+
+impl Hash for HdrImage {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl Deref for HdrImage {
+    type Target = Rgb32FImage;
+
+    fn deref(&self) -> &Self::Target {
+        &self.image
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HdrImageData {
+    width: u32,
+    height: u32,
+    data: Vec<f32>,
+    hash: u64,
+    // Missing from files saved before HDR images could be filtered
+    #[serde(default)]
+    sampler: Sampler,
+    #[serde(default = "linear")]
+    colour_space: ColourSpace,
+}
+
+fn linear() -> ColourSpace {
+    ColourSpace::Linear
+}
+
+impl From<HdrImage> for HdrImageData {
+    fn from(value: HdrImage) -> Self {
+        Self {
+            width: value.width(),
+            height: value.height(),
+            data: value.as_raw().clone(),
+            hash: value.hash,
+            sampler: value.sampler,
+            colour_space: value.colour_space,
+        }
+    }
+}
+
+impl TryFrom<HdrImageData> for HdrImage {
+    type Error = &'static str;
+
+    fn try_from(value: HdrImageData) -> Result<Self, Self::Error> {
+        let image = Rgb32FImage::from_raw(value.width, value.height, value.data)
+            .ok_or("Size and data should match")?;
+        Ok(Self {
+            image: Arc::new(image),
+            hash: value.hash,
+            sampler: value.sampler,
+            colour_space: value.colour_space,
+            mips: Default::default(),
+            distribution: Default::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_with_wrong_alpha_size_fails_to_load() {
+        let image = |alpha_len| {
+            rmp_serde::to_vec(&(
+                2u32,
+                2u32,
+                vec![0u8; 12],
+                0u64,
+                Sampler::default(),
+                ColourSpace::Srgb,
+                Some(vec![0u8; alpha_len]),
+            ))
+            .unwrap()
+        };
+        assert!(rmp_serde::from_slice::<Image>(&image(4)).is_ok());
+        assert!(rmp_serde::from_slice::<Image>(&image(3)).is_err());
+    }
+}
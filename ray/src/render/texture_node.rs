@@ -0,0 +1,253 @@
+use std::cell::Cell;
+
+use cgmath::{ElementWise, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::vec_repo::{Id, VecRepo};
+
+use super::{
+    procedural::ColourRamp,
+    texture::{TexCoord, Texture},
+    Colour,
+};
+
+// Textures built from other textures. None of them may end up reading itself.
+
+/// Deeper than any sensible graph of textures, so that a cycle of them, which the
+/// editor won't make but a file might hold, can't overflow the stack.
+const MAX_INPUTS: u32 = 16;
+
+thread_local! {
+    /// How deep inputs nest, and whether the lookup under way has gone past `MAX_INPUTS`.
+    static INPUTS: Cell<(u32, bool)> = const { Cell::new((0, false)) };
+}
+
+/// A texture another one is built from. Reads through it give black, and full
+/// coverage, once inputs nest past `MAX_INPUTS`. The rest of that lookup gives
+/// up straight away, as a cycle through textures with several inputs would
+/// otherwise branch out into far too many reads.
+struct Input<'a>(&'a Texture);
+
+fn input(textures: &VecRepo<Texture>, id: Id<Texture>) -> Input<'_> {
+    Input(textures.get(id))
+}
+
+impl Input<'_> {
+    fn nested<R>(&self, fallback: R, f: impl FnOnce(&Texture) -> R) -> R {
+        let (depth, cut) = INPUTS.get();
+        if cut || depth >= MAX_INPUTS {
+            INPUTS.set((depth, true));
+            return fallback;
+        }
+        INPUTS.set((depth + 1, false));
+        let result = f(self.0);
+        let (_, cut) = INPUTS.get();
+        // Back at the top, the next lookup starts afresh
+        INPUTS.set((depth, cut && depth > 0));
+        result
+    }
+
+    fn colour_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> Colour {
+        self.nested(Colour::zero(), |t| t.colour_at(at, textures))
+    }
+
+    fn value_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> f64 {
+        self.nested(0.0, |t| t.value_at(at, textures))
+    }
+
+    fn alpha_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> f64 {
+        self.nested(1.0, |t| t.alpha_at(at, textures))
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Multiply {
+    pub a: Id<Texture>,
+    pub b: Id<Texture>,
+}
+
+impl Multiply {
+    pub fn colour_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> Colour {
+        let a = input(textures, self.a).colour_at(at, textures);
+        a.mul_element_wise(input(textures, self.b).colour_at(at, textures))
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Add {
+    pub a: Id<Texture>,
+    pub b: Id<Texture>,
+}
+
+impl Add {
+    pub fn colour_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> Colour {
+        input(textures, self.a).colour_at(at, textures)
+            + input(textures, self.b).colour_at(at, textures)
+    }
+}
+
+/// `a` where the luminance of `mask` is 0, `b` where it is 1.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lerp {
+    pub a: Id<Texture>,
+    pub b: Id<Texture>,
+    pub mask: Id<Texture>,
+}
+
+impl Lerp {
+    pub fn colour_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> Colour {
+        let t = input(textures, self.mask)
+            .value_at(at, textures)
+            .clamp(0.0, 1.0) as f32;
+        let a = input(textures, self.a).colour_at(at, textures);
+        let b = input(textures, self.b).colour_at(at, textures);
+        a + (b - a) * t
+    }
+}
+
+/// Luminance of `input` mapped through a ramp, to recolour greyscale masks and noise.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ramp {
+    pub input: Id<Texture>,
+    pub ramp: ColourRamp,
+}
+
+impl Ramp {
+    pub fn colour_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> Colour {
+        self.ramp
+            .colour_at(input(textures, self.input).value_at(at, textures))
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct HueSaturation {
+    pub input: Id<Texture>,
+    /// Turn around the colour wheel, in degrees.
+    pub hue: f64,
+    pub saturation: f64,
+    pub value: f64,
+}
+
+impl HueSaturation {
+    pub fn new(input: Id<Texture>) -> Self {
+        Self {
+            input,
+            hue: 0.0,
+            saturation: 1.0,
+            value: 1.0,
+        }
+    }
+
+    pub fn colour_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> Colour {
+        let c = input(textures, self.input).colour_at(at, textures);
+        let (h, s, v) = rgb_to_hsv(c);
+        hsv_to_rgb(
+            (h + self.hue / 360.0).rem_euclid(1.0),
+            (s * self.saturation).clamp(0.0, 1.0),
+            v * self.value,
+        )
+    }
+}
+
+/// One minus each channel.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Invert {
+    pub input: Id<Texture>,
+}
+
+impl Invert {
+    pub fn colour_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> Colour {
+        Colour::new(1.0, 1.0, 1.0) - input(textures, self.input).colour_at(at, textures)
+    }
+}
+
+/// Moves surface coordinates before `input` is looked up: scaled, turned about
+/// the origin, then offset. Solid textures are left alone.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct UvTransform {
+    pub input: Id<Texture>,
+    pub scale: (f64, f64),
+    /// In degrees.
+    pub rotation: f64,
+    pub offset: (f64, f64),
+}
+
+impl UvTransform {
+    pub fn new(input: Id<Texture>) -> Self {
+        Self {
+            input,
+            scale: (1.0, 1.0),
+            rotation: 0.0,
+            offset: (0.0, 0.0),
+        }
+    }
+
+    pub fn colour_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> Colour {
+        input(textures, self.input).colour_at(self.transform(at), textures)
+    }
+
+    pub fn alpha_at(&self, at: TexCoord, textures: &VecRepo<Texture>) -> f64 {
+        input(textures, self.input).alpha_at(self.transform(at), textures)
+    }
+
+    fn transform(&self, at: TexCoord) -> TexCoord {
+        let (u, v) = (at.u * self.scale.0, at.v * self.scale.1);
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        TexCoord {
+            u: u * cos - v * sin + self.offset.0,
+            v: u * sin + v * cos + self.offset.1,
+            footprint: (
+                at.footprint.0 * self.scale.0.abs(),
+                at.footprint.1 * self.scale.1.abs(),
+            ),
+            ..at
+        }
+    }
+}
+
+/// Hue as a fraction of a turn, saturation and value.
+fn rgb_to_hsv(c: Colour) -> (f64, f64, f64) {
+    let (r, g, b) = (c.x as f64, c.y as f64, c.z as f64);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    let saturation = if max > 0.0 { delta / max } else { 0.0 };
+    (hue / 6.0, saturation, max)
+}
+
+fn hsv_to_rgb(h: f64, s: f64, v: f64) -> Colour {
+    let channel = |n: f64| {
+        let k = (n + h * 6.0) % 6.0;
+        (v - v * s * k.min(4.0 - k).clamp(0.0, 1.0)) as f32
+    };
+    Colour::new(channel(5.0), channel(3.0), channel(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cyclic_inputs_read_as_black() {
+        let mut textures = VecRepo::<Texture>::default();
+        let a = textures.insert(Colour::zero());
+        let b = textures.insert(Multiply { a, b: a });
+        *textures.get_mut(a).unwrap() = Lerp { a: b, b, mask: a }.into();
+        let transform = textures.insert(Colour::zero());
+        *textures.get_mut(transform).unwrap() = UvTransform::new(transform).into();
+
+        let at = TexCoord::uv(0.5, 0.5);
+        assert_eq!(textures.get(a).colour_at(at, &textures), Colour::zero());
+        assert_eq!(textures.get(transform).alpha_at(at, &textures), 1.0);
+        assert_eq!(INPUTS.get(), (0, false));
+    }
+}
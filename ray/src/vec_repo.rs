@@ -1,8 +1,13 @@
-use std::fmt::Display;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Id<T, IdT = u32> {
     id: IdT,
     phantom: std::marker::PhantomData<fn(T)>,
@@ -16,6 +21,32 @@ impl<T> Clone for Id<T> {
     }
 }
 
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> PartialOrd for Id<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Id<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl<T> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
 impl<T> Id<T> {
     pub fn new(n: impl Into<u32>) -> Self {
         Self {
@@ -31,15 +62,21 @@ impl<T> Display for Id<T> {
     }
 }
 
+/// Values addressed by `Id`s, the first one standing in for anything missing.
+/// Removed values leave an empty slot behind, so an `Id` never comes to mean another value.
+/// Values may also be given names, no two alike, to be found by.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct VecRepo<T> {
     data: Vec<Option<T>>,
+    #[serde(default)]
+    names: Names<Id<T>>,
 }
 
 impl<T: Default> Default for VecRepo<T> {
     fn default() -> Self {
         Self {
             data: vec![Some(Default::default())],
+            names: Names::default(),
         }
     }
 }
@@ -48,6 +85,7 @@ impl<T> VecRepo<T> {
     pub fn new(default: T) -> Self {
         Self {
             data: vec![Some(default)],
+            names: Names::default(),
         }
     }
 
@@ -57,16 +95,30 @@ impl<T> VecRepo<T> {
         Id::new(id)
     }
 
+    /// Panics if `name` is already given to another value.
+    pub fn insert_named(&mut self, name: impl Into<String>, value: impl Into<T>) -> Id<T> {
+        let name = name.into();
+        assert!(self.find(&name).is_none(), "name {:?} is taken", name);
+        let id = self.insert(value);
+        self.names.set(id, name);
+        id
+    }
+
     pub fn get_default(&self) -> &T {
         unsafe { self.data.get_unchecked(0).as_ref().unwrap_unchecked() }
     }
 
+    /// The default value if `key` was removed or never inserted.
     pub fn get(&self, key: Id<T>) -> &T {
-        let x = self.data.get(key.id as usize);
-        match x {
-            None => self.get_default(),
-            Some(x) => x.as_ref().unwrap_or(self.get_default()),
-        }
+        self.try_get(key).unwrap_or(self.get_default())
+    }
+
+    pub fn try_get(&self, key: Id<T>) -> Option<&T> {
+        self.data.get(key.id as usize)?.as_ref()
+    }
+
+    pub fn contains(&self, key: Id<T>) -> bool {
+        self.try_get(key).is_some()
     }
 
     pub fn get_mut(&mut self, key: Id<T>) -> Option<&mut T> {
@@ -77,11 +129,37 @@ impl<T> VecRepo<T> {
         }
     }
 
+    /// Empties the slot of `key`, which is never handed out again.
+    /// The default value can't be removed.
     pub fn remove(&mut self, key: Id<T>) -> Option<T> {
-        if self.data.len() == 1 {
+        if key.id == 0 {
             return None;
         }
-        self.data.remove(key.id as usize)
+        let value = self.data.get_mut(key.id as usize)?.take();
+        self.names.forget(key);
+        value
+    }
+
+    pub fn name(&self, key: Id<T>) -> Option<&str> {
+        self.names.name(key)
+    }
+
+    /// Names `key`, replacing the name it had. An empty name leaves it unnamed.
+    /// Returns false, changing nothing, if another value has the name or `key` has no value.
+    pub fn set_name(&mut self, key: Id<T>, name: impl Into<String>) -> bool {
+        self.contains(key) && self.names.set(key, name)
+    }
+
+    pub fn find(&self, name: &str) -> Option<Id<T>> {
+        self.names.find(name)
+    }
+
+    pub fn names(&self) -> &Names<Id<T>> {
+        &self.names
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&T> {
+        self.try_get(self.find(name)?)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &T> {
@@ -91,4 +169,225 @@ impl<T> VecRepo<T> {
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
         self.data.iter_mut().filter_map(|x| x.as_mut())
     }
+
+    /// Values with their ids, skipping removed ones.
+    pub fn iter_with_ids(&self) -> impl Iterator<Item = (Id<T>, &T)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter_map(|(id, x)| Some((Id::new(id as u32), x.as_ref()?)))
+    }
+
+    pub fn iter_with_ids_mut(&mut self) -> impl Iterator<Item = (Id<T>, &mut T)> {
+        self.data
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(id, x)| Some((Id::new(id as u32), x.as_mut()?)))
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = Id<T>> + '_ {
+        self.iter_with_ids().map(|(id, _)| id)
+    }
+
+    /// Turns every value into a `U`, keeping ids, empty slots and names as they are.
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> VecRepo<U> {
+        VecRepo {
+            data: self.data.into_iter().map(|x| x.map(&mut f)).collect(),
+            names: Names {
+                keys: self
+                    .names
+                    .keys
+                    .into_iter()
+                    .map(|(name, id)| (name, Id::new(id.id)))
+                    .collect(),
+            },
+        }
+    }
+
+    /// Drops empty slots and the values `keep` turns down, moving the rest together.
+    /// Gives the new id of every value left, the default staying first.
+    pub fn compact(&mut self, keep: impl Fn(Id<T>) -> bool) -> HashMap<Id<T>, Id<T>> {
+        let mut moved = HashMap::new();
+        let mut data = Vec::new();
+        for (id, value) in std::mem::take(&mut self.data).into_iter().enumerate() {
+            let id = Id::new(id as u32);
+            match value {
+                Some(value) if id.id == 0 || keep(id) => {
+                    moved.insert(id, Id::new(data.len() as u32));
+                    data.push(Some(value));
+                }
+                _ => {}
+            }
+        }
+        self.data = data;
+        self.names.remap(|id| moved.get(&id).copied());
+        moved
+    }
+}
+
+/// Unique names for keys of some collection, each key having at most one.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Names<K> {
+    keys: BTreeMap<String, K>,
+}
+
+impl<K> Default for Names<K> {
+    fn default() -> Self {
+        Self {
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Copy + PartialEq> Names<K> {
+    pub fn name(&self, key: K) -> Option<&str> {
+        self.keys
+            .iter()
+            .find(|(_, k)| **k == key)
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn find(&self, name: &str) -> Option<K> {
+        self.keys.get(name).copied()
+    }
+
+    /// Names `key`, replacing the name it had. An empty name leaves it unnamed.
+    /// Returns false, changing nothing, if another key has the name.
+    pub fn set(&mut self, key: K, name: impl Into<String>) -> bool {
+        let name = name.into();
+        match self.keys.get(&name) {
+            Some(k) if *k == key => return true,
+            Some(_) => return false,
+            None => {}
+        }
+        self.forget(key);
+        if !name.is_empty() {
+            self.keys.insert(name, key);
+        }
+        true
+    }
+
+    pub fn forget(&mut self, key: K) {
+        self.keys.retain(|_, k| *k != key);
+    }
+
+    /// Moves every name to the key `f` gives for its old one, dropping it if there is none.
+    pub fn remap(&mut self, f: impl Fn(K) -> Option<K>) {
+        let keys = std::mem::take(&mut self.keys);
+        self.keys = keys
+            .into_iter()
+            .filter_map(|(name, key)| Some((name, f(key)?)))
+            .collect();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, K)> {
+        self.keys.iter().map(|(name, key)| (name.as_str(), *key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo() -> (VecRepo<u32>, Vec<Id<u32>>) {
+        let mut repo = VecRepo::new(0);
+        let ids = (1..=4u32).map(|n| repo.insert(n * 10)).collect();
+        (repo, ids)
+    }
+
+    #[test]
+    fn removal_leaves_a_tombstone() {
+        let (mut repo, ids) = repo();
+        assert_eq!(repo.remove(ids[1]), Some(20));
+        assert_eq!(repo.remove(ids[1]), None);
+        assert_eq!(repo.try_get(ids[1]), None);
+        assert_eq!(*repo.get(ids[1]), 0, "removed ids read the default");
+        assert_eq!(repo.try_get(ids[2]), Some(&30), "later ids stay put");
+
+        let id = repo.insert(50u32);
+        assert!(!ids.contains(&id), "a removed id is never handed out again");
+        assert_eq!(
+            repo.ids().collect::<Vec<_>>(),
+            [Id::new(0u32), ids[0], ids[2], ids[3], id]
+        );
+    }
+
+    #[test]
+    fn default_cannot_be_removed() {
+        let (mut repo, _) = repo();
+        assert_eq!(repo.remove(Id::new(0u32)), None);
+        assert_eq!(repo.try_get(Id::new(0u32)), Some(&0));
+        assert_eq!(repo.try_get(Id::new(99u32)), None);
+    }
+
+    #[test]
+    fn compact_closes_gaps_and_reports_moves() {
+        let (mut repo, ids) = repo();
+        repo.remove(ids[1]);
+        let moved = repo.compact(|id| id != ids[2]);
+        let mut moved: Vec<_> = moved.into_iter().collect();
+        moved.sort();
+        assert_eq!(
+            moved,
+            [
+                (Id::new(0u32), Id::new(0u32)),
+                (ids[0], Id::new(1u32)),
+                (ids[3], Id::new(2u32)),
+            ]
+        );
+        assert_eq!(repo.iter().copied().collect::<Vec<_>>(), [0, 10, 40]);
+    }
+
+    #[test]
+    fn compact_keeps_the_default() {
+        let (mut repo, _) = repo();
+        repo.compact(|_| false);
+        assert_eq!(repo.iter().copied().collect::<Vec<_>>(), [0]);
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let mut names = Names::default();
+        assert!(names.set(1, "floor"));
+        assert!(names.set(1, "floor"), "a key may keep its own name");
+        assert!(!names.set(2, "floor"));
+        assert_eq!(names.find("floor"), Some(1));
+        assert_eq!(names.name(2), None);
+
+        assert!(names.set(1, "ground"));
+        assert_eq!(names.find("floor"), None, "renaming drops the old name");
+        assert!(names.set(2, "floor"));
+
+        assert!(names.set(1, ""));
+        assert_eq!(names.name(1), None, "an empty name clears it");
+        assert_eq!(names.iter().collect::<Vec<_>>(), [("floor", 2)]);
+    }
+
+    #[test]
+    fn repo_names_follow_their_values() {
+        let (mut repo, ids) = repo();
+        assert!(repo.set_name(ids[0], "a"));
+        assert!(repo.set_name(ids[2], "c"));
+        assert!(!repo.set_name(ids[3], "a"));
+        assert!(!repo.set_name(Id::new(99u32), "x"), "nothing to name");
+        assert_eq!(repo.get_by_name("c"), Some(&30));
+
+        repo.remove(ids[0]);
+        assert_eq!(repo.find("a"), None, "removal forgets the name");
+        assert!(repo.set_name(ids[3], "a"));
+
+        repo.compact(|id| id != ids[2]);
+        assert_eq!(repo.find("c"), None);
+        assert_eq!(repo.find("a"), Some(Id::new(2u32)));
+        assert_eq!(repo.get_by_name("a"), Some(&40));
+    }
+
+    #[test]
+    #[should_panic(expected = "is taken")]
+    fn insert_named_rejects_a_taken_name() {
+        let mut repo = VecRepo::new(0u32);
+        repo.insert_named("a", 1u32);
+        repo.insert_named("a", 2u32);
+    }
 }